@@ -0,0 +1,35 @@
+//! Renders a small scene without opening a window and saves it as a PNG.
+//!
+//! cargo run --example headless -- scene.png
+
+use wgpu_lyon::canvas::{parse_color, Canvas2d};
+use wgpu_lyon::headless::{HeadlessOptions, HeadlessRenderer};
+
+fn main() {
+    env_logger::init();
+    let output = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "scene.png".to_string());
+
+    let mut ctx = Canvas2d::new();
+    ctx.set_fill_style(parse_color("#2a9d8f").unwrap());
+    ctx.fill_rect(50.0, 50.0, 200.0, 100.0);
+    ctx.begin_path();
+    ctx.move_to(250.0, 150.0);
+    ctx.line_to(350.0, 150.0);
+    ctx.stroke();
+    ctx.set_fill_style(parse_color("#e9c46a").unwrap());
+    ctx.fill_rect(150.0, 100.0, 150.0, 75.0);
+    let scene = ctx.into_scene();
+
+    let options = HeadlessOptions {
+        force_fallback_adapter: std::env::var_os("FORCE_FALLBACK_ADAPTER").is_some(),
+        ..Default::default()
+    };
+    let mut renderer = pollster::block_on(HeadlessRenderer::new(400, 250, &options))
+        .expect("Couldn't create a headless renderer");
+    let camera = renderer.default_camera();
+    let image = renderer.render(&scene, &camera);
+    image.save_png(&output).expect("Couldn't write the PNG");
+    println!("Wrote {}", output);
+}
@@ -0,0 +1,28 @@
+//! Opens a window showing an SVG file, drawn at its own size from the top
+//! left corner.
+//!
+//! cargo run --example svg_viewer -- drawing.svg
+
+use wgpu_lyon::scene::Scene;
+use wgpu_lyon::svg;
+use wgpu_lyon::RenderOptions;
+
+fn main() {
+    env_logger::init();
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("Usage: svg_viewer <file.svg>");
+        std::process::exit(2);
+    };
+    let text = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+        eprintln!("Couldn't read {}: {}", path, e);
+        std::process::exit(1);
+    });
+
+    let mut scene = Scene::new();
+    scene.background = [1.0, 1.0, 1.0, 1.0];
+    if let Err(e) = svg::import(&text, &mut scene) {
+        eprintln!("Couldn't load {}: {}", path, e);
+        std::process::exit(1);
+    }
+    pollster::block_on(wgpu_lyon::run_with_scene(scene, RenderOptions::default()));
+}
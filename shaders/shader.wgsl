@@ -1,28 +1,199 @@
 // Vertex shader
 
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    // Scene units per device pixel.
+    pixel_size: f32,
+    // How far, in device pixels, edges are pushed out to be faded. Zero when
+    // feathering is off.
+    feather: f32,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
-    @location(1) color: vec3<f32>,
+    // Premultiplied RGBA.
+    @location(1) color: vec4<f32>,
+    @location(2) normal: vec2<f32>,
+    @location(3) side: f32,
+    @location(4) half_width: f32,
+    // Position in the shape's path space.
+    @location(5) local: vec2<f32>,
+};
+
+// Must match InstanceRaw in src/lib.rs.
+struct InstanceInput {
+    // Columns of the 2x3 matrix applied to the vertex position.
+    @location(6) transform_x: vec2<f32>,
+    @location(7) transform_y: vec2<f32>,
+    @location(8) translation: vec2<f32>,
+    // Premultiplied RGBA.
+    @location(9) tint: vec4<f32>,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
-    @location(0) color: vec3<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) normal: vec2<f32>,
+    @location(2) side: f32,
+    // Half the width of the feathered strip in device pixels, zero for
+    // geometry that isn't faded.
+    @location(3) half_width: f32,
+    @location(4) local: vec2<f32>,
 };
 
 @vertex
 fn vs_main(
     model: VertexInput,
+    instance: InstanceInput,
 ) -> VertexOutput {
+    let placement = mat2x2<f32>(instance.transform_x, instance.transform_y);
+    // Normals and widths scale like the vertex tessellation does with shape
+    // transforms, see edge_normal.
+    let scale = sqrt(abs(determinant(placement)));
+    let normal = placement * model.normal / max(scale, 1e-12);
+
     var out: VertexOutput;
-    out.color = model.color;
-    out.clip_position = vec4<f32>(model.position, 1.0);
+    out.color = model.color * instance.tint;
+    out.normal = normal;
+    out.side = model.side;
+    out.local = model.local;
+    out.half_width = 0.0;
+    var position = placement * model.position.xy + instance.translation;
+    if (camera.feather > 0.0 && dot(normal, normal) > 0.0) {
+        // Vertices with a zero side stay on the outline, see
+        // Tessellator::feather.
+        position = position + normal * abs(model.side) * camera.feather * camera.pixel_size;
+        out.half_width = model.half_width * scale / camera.pixel_size + camera.feather;
+    }
+    out.clip_position = camera.view_proj * vec4<f32>(position, model.position.z, 1.0);
     return out;
 }
 
 // Fragment shader
 
+// Must match PaintUniform in src/paint.rs.
+struct Paint {
+    // 0: solid, the vertex color alone. 1: linear gradient. 2: radial
+    // gradient. 3: image.
+    kind: u32,
+    // 0: pad, 1: repeat, 2: reflect. Images repeat with 1.
+    spread: u32,
+    stop_count: u32,
+    // Rows of the 2x3 matrix from path space to gradient space, or to
+    // texture coordinates for images.
+    transform_x: vec4<f32>,
+    transform_y: vec4<f32>,
+    // Linear: start and end. Radial: center and focal point.
+    points: vec4<f32>,
+    radius: f32,
+    // Four per vector.
+    offsets: array<vec4<f32>, 2>,
+    // Premultiplied RGBA.
+    colors: array<vec4<f32>, 8>,
+};
+@group(1) @binding(0)
+var<uniform> paint: Paint;
+
+// Straight alpha, sRGB decoded when sampled. The sampler repeats or clamps
+// to match the paint, as WebGL can't use a texture with two samplers.
+@group(2) @binding(0)
+var image: texture_2d<f32>;
+@group(2) @binding(1)
+var image_sampler: sampler;
+
+fn paint_space(p: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(
+        dot(vec3<f32>(p, 1.0), paint.transform_x.xyz),
+        dot(vec3<f32>(p, 1.0), paint.transform_y.xyz),
+    );
+}
+
+fn image_color(p: vec2<f32>) -> vec4<f32> {
+    let uv = paint_space(p);
+    if (paint.spread != 1u && (any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)))) {
+        return vec4<f32>(0.0);
+    }
+    let texel = textureSampleLevel(image, image_sampler, uv, 0.0);
+    return vec4<f32>(texel.rgb * texel.a, texel.a);
+}
+
+// Where `p` falls along the gradient, before spreading.
+fn gradient_offset(p: vec2<f32>) -> f32 {
+    let q = paint_space(p);
+    if (paint.kind == 1u) {
+        let start = paint.points.xy;
+        let axis = paint.points.zw - start;
+        return dot(q - start, axis) / max(dot(axis, axis), 1e-12);
+    }
+    // The ray from the focal point through q leaves the circle at distance
+    // s, and q is at distance / s of the way there.
+    let center = paint.points.xy;
+    let focal = paint.points.zw;
+    let distance = length(q - focal);
+    if (distance == 0.0) {
+        return 0.0;
+    }
+    let direction = (q - focal) / distance;
+    let to_center = center - focal;
+    let b = dot(direction, to_center);
+    let c = dot(to_center, to_center) - paint.radius * paint.radius;
+    let s = b + sqrt(max(b * b - c, 0.0));
+    return distance / max(s, 1e-12);
+}
+
+fn spread(t: f32) -> f32 {
+    if (paint.spread == 1u) {
+        return fract(t);
+    }
+    if (paint.spread == 2u) {
+        return 1.0 - abs(fract(t * 0.5) * 2.0 - 1.0);
+    }
+    return clamp(t, 0.0, 1.0);
+}
+
+fn stop_offset(i: u32) -> f32 {
+    return paint.offsets[i / 4u][i % 4u];
+}
+
+// Premultiplied color the paint has at `p`, in path space.
+fn paint_color(p: vec2<f32>) -> vec4<f32> {
+    if (paint.kind == 0u) {
+        return vec4<f32>(1.0);
+    }
+    if (paint.kind == 3u) {
+        return image_color(p);
+    }
+    if (paint.stop_count == 0u) {
+        return vec4<f32>(0.0);
+    }
+    let t = spread(gradient_offset(p));
+    var color = paint.colors[0];
+    for (var i = 1u; i < paint.stop_count; i = i + 1u) {
+        let start = stop_offset(i - 1u);
+        let end = stop_offset(i);
+        if (t >= end) {
+            color = paint.colors[i];
+        } else if (t > start) {
+            color = mix(paint.colors[i - 1u], paint.colors[i], (t - start) / (end - start));
+        }
+    }
+    return color;
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(in.color, 1.0);
+    var alpha = 1.0;
+    if (in.half_width > 0.0) {
+        // How far across the strip the fragment is: 0 in the middle, or on
+        // the outline for the strips around fills, and 1 on its sides. The
+        // side is exact along straight runs, miters included, and the
+        // normal's length along round joins and caps, whose triangles can
+        // have all their vertices on one side.
+        let across = min(abs(in.side), length(in.normal));
+        // Coverage ramps up over the outermost device pixel.
+        alpha = clamp((1.0 - across) * in.half_width, 0.0, 1.0);
+    }
+    return paint_color(in.local) * in.color * alpha;
 }
@@ -0,0 +1,113 @@
+//! Merges the draws of many shapes into few draw calls.
+
+use std::ops::Range;
+
+use lyon::math::Box2D;
+
+use crate::tessellate::{Draw, DrawKind};
+
+// How many batches back a draw looks for one to join. Keeps scenes where
+// little can be merged from taking quadratic time.
+const LOOKBACK: usize = 256;
+
+/// Collects draws in painting order. A draw joins an earlier batch with the
+/// same paint, image, stencil value and instances, as long as nothing drawn
+/// after that batch touches its bounds: drawing it earlier then can't
+/// change the picture. Clips are never merged and nothing moves past them.
+pub struct Batcher {
+    batches: Vec<Batch>,
+    // The first batch draws may still join. Those before it come before a
+    // change to the stencil buffer.
+    open: usize,
+}
+
+struct Batch {
+    draw: Draw,
+    // Ranges of the tessellated indices, in the order they are drawn.
+    ranges: Vec<Range<u32>>,
+    // Everything the batch may paint, in scene space.
+    bounds: Box2D,
+}
+
+impl Batcher {
+    pub fn new() -> Self {
+        Self {
+            batches: Vec::new(),
+            open: 0,
+        }
+    }
+
+    /// Adds `draw`, which paints nothing outside `bounds`.
+    pub fn push(&mut self, draw: Draw, bounds: Box2D) {
+        if draw.kind != DrawKind::Paint {
+            self.batches.push(Batch {
+                ranges: vec![draw.indices.clone()],
+                draw,
+                bounds,
+            });
+            self.open = self.batches.len();
+            return;
+        }
+        let open = self.open.max(self.batches.len().saturating_sub(LOOKBACK));
+        // The earliest batch to join, leaving later ones free for the draws
+        // that will have to come after this one.
+        let mut target = None;
+        for (i, batch) in self.batches.iter().enumerate().skip(open).rev() {
+            if batch.accepts(&draw) {
+                target = Some(i);
+            }
+            if touches(&batch.bounds, &bounds) {
+                break;
+            }
+        }
+        match target {
+            Some(i) => {
+                let batch = &mut self.batches[i];
+                match batch.ranges.last_mut() {
+                    Some(last) if last.end == draw.indices.start => last.end = draw.indices.end,
+                    _ => batch.ranges.push(draw.indices),
+                }
+                batch.bounds = batch.bounds.union(&bounds);
+            }
+            None => self.batches.push(Batch {
+                ranges: vec![draw.indices.clone()],
+                draw,
+                bounds,
+            }),
+        }
+    }
+
+    /// One draw per batch, and `indices` rearranged so that each batch's
+    /// are next to each other.
+    pub fn finish(self, indices: &[u32]) -> (Vec<u32>, Vec<Draw>) {
+        let mut batched = Vec::with_capacity(indices.len());
+        let mut draws = Vec::with_capacity(self.batches.len());
+        for batch in self.batches {
+            let start = batched.len() as u32;
+            for range in &batch.ranges {
+                batched.extend_from_slice(&indices[range.start as usize..range.end as usize]);
+            }
+            draws.push(Draw {
+                indices: start..batched.len() as u32,
+                ..batch.draw
+            });
+        }
+        (batched, draws)
+    }
+}
+
+impl Batch {
+    fn accepts(&self, draw: &Draw) -> bool {
+        self.draw.kind == DrawKind::Paint
+            && self.draw.paint == draw.paint
+            && self.draw.image == draw.image
+            && self.draw.stencil == draw.stencil
+            && self.draw.instances == draw.instances
+    }
+}
+
+// Like Box2D::intersects, but also true for boxes sharing only an edge, as
+// both may cover a pixel along it.
+fn touches(a: &Box2D, b: &Box2D) -> bool {
+    a.min.x <= b.max.x && b.min.x <= a.max.x && a.min.y <= b.max.y && b.min.y <= a.max.y
+}
@@ -0,0 +1,423 @@
+//! GPU buffers for the scene that are updated in place, so a change to one
+//! shape only uploads that shape's vertices, along with the indices, paints
+//! and instances that moved or changed.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::tessellate::{Source, Tessellation};
+use crate::Vertex;
+
+// Room for this many vertices and indices at first. Buffers double whenever
+// they run out.
+const INITIAL_VERTICES: u32 = 1 << 12;
+const INITIAL_INDICES: u32 = 1 << 14;
+const INITIAL_SLOTS: usize = 1 << 6;
+
+// Changed ranges of vertices or slots closer than this are written together,
+// as one larger write is cheaper than many small ones.
+const MERGE_GAP: u32 = 64;
+
+/// Hands out ranges of slots in a buffer, first fit.
+pub struct FreeList {
+    capacity: u32,
+    // Sorted, and never touching: freeing merges neighbours.
+    free: Vec<Range<u32>>,
+}
+
+impl FreeList {
+    pub fn new(capacity: u32) -> Self {
+        let mut list = Self {
+            capacity: 0,
+            free: Vec::new(),
+        };
+        list.grow(capacity);
+        list
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// The first `len` free slots in a row, or None if there aren't that many.
+    pub fn allocate(&mut self, len: u32) -> Option<Range<u32>> {
+        let index = self.free.iter().position(|r| r.len() as u32 >= len)?;
+        let range = &mut self.free[index];
+        let start = range.start;
+        range.start += len;
+        if range.start == range.end {
+            self.free.remove(index);
+        }
+        Some(start..start + len)
+    }
+
+    pub fn free(&mut self, range: Range<u32>) {
+        if range.start >= range.end {
+            return;
+        }
+        let index = self.free.partition_point(|r| r.start < range.start);
+        let joins_previous = index > 0 && self.free[index - 1].end == range.start;
+        let joins_next = index < self.free.len() && self.free[index].start == range.end;
+        match (joins_previous, joins_next) {
+            (true, true) => {
+                self.free[index - 1].end = self.free[index].end;
+                self.free.remove(index);
+            }
+            (true, false) => self.free[index - 1].end = range.end,
+            (false, true) => self.free[index].start = range.start,
+            (false, false) => self.free.insert(index, range),
+        }
+    }
+
+    /// Adds slots at the end, up to `capacity`.
+    pub fn grow(&mut self, capacity: u32) {
+        if capacity > self.capacity {
+            self.free(self.capacity..capacity);
+            self.capacity = capacity;
+        }
+    }
+}
+
+/// The vertex and index buffers the renderer draws from.
+///
+/// Every shape and clip gets its own range of the vertex buffer, kept as
+/// long as it has the same number of vertices, and only written when they
+/// changed. Indices point into those ranges; only the part of the index
+/// buffer that differs from the last upload is written.
+pub struct GeometryBuffers {
+    vertex_buffer: wgpu::Buffer,
+    // What the vertex buffer holds, to tell which vertices changed.
+    vertices: Vec<Vertex>,
+    slots: FreeList,
+    ranges: HashMap<Source, Range<u32>>,
+    index_buffer: wgpu::Buffer,
+    // Uint16 as long as every vertex slot fits in one.
+    index_format: wgpu::IndexFormat,
+    // What the index buffer holds, and how many it has room for.
+    indices: Vec<u32>,
+    index_capacity: u32,
+}
+
+impl GeometryBuffers {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let index_format = index_format(INITIAL_VERTICES);
+        Self {
+            vertex_buffer: create_vertex_buffer(device, INITIAL_VERTICES),
+            vertices: vec![bytemuck::Zeroable::zeroed(); INITIAL_VERTICES as usize],
+            slots: FreeList::new(INITIAL_VERTICES),
+            ranges: HashMap::new(),
+            index_buffer: create_index_buffer(device, index_format, INITIAL_INDICES),
+            index_format,
+            indices: Vec::new(),
+            index_capacity: INITIAL_INDICES,
+        }
+    }
+
+    pub fn vertex_buffer(&self) -> &wgpu::Buffer {
+        &self.vertex_buffer
+    }
+
+    pub fn index_buffer(&self) -> &wgpu::Buffer {
+        &self.index_buffer
+    }
+
+    pub fn index_format(&self) -> wgpu::IndexFormat {
+        self.index_format
+    }
+
+    /// Makes the buffers hold the geometry of `tessellation`, writing as
+    /// little as possible. The indices of its draws can be used as they are.
+    pub fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        tessellation: &Tessellation,
+    ) {
+        let geometry = &tessellation.geometry;
+
+        // Ranges are kept for sources still there with as many vertices,
+        // the others are freed first so their space can be reused.
+        let mut ranges = HashMap::with_capacity(tessellation.spans.len());
+        for span in &tessellation.spans {
+            if let Some(range) = self.ranges.remove(&span.source) {
+                if range.len() == span.vertices.len() {
+                    ranges.insert(span.source, range);
+                    continue;
+                }
+                self.slots.free(range);
+            }
+        }
+        for (_, range) in self.ranges.drain() {
+            self.slots.free(range);
+        }
+
+        let mut grown = false;
+        let mut dirty = Vec::new();
+        // Where each tessellated vertex went.
+        let mut slot_of = vec![0; geometry.vertices.len()];
+        for span in &tessellation.spans {
+            let len = span.vertices.len() as u32;
+            let range = match ranges.get(&span.source) {
+                Some(range) => range.clone(),
+                None => {
+                    let range = match self.slots.allocate(len) {
+                        Some(range) => range,
+                        None => {
+                            let capacity = self.slots.capacity();
+                            self.slots.grow((capacity + len).next_power_of_two());
+                            self.vertices.resize(
+                                self.slots.capacity() as usize,
+                                bytemuck::Zeroable::zeroed(),
+                            );
+                            grown = true;
+                            self.slots.allocate(len).unwrap()
+                        }
+                    };
+                    ranges.insert(span.source, range.clone());
+                    range
+                }
+            };
+            let new = &geometry.vertices[span.vertices.start as usize..span.vertices.end as usize];
+            let old = &mut self.vertices[range.start as usize..range.end as usize];
+            if bytemuck::cast_slice::<Vertex, u8>(old) != bytemuck::cast_slice::<Vertex, u8>(new) {
+                old.copy_from_slice(new);
+                dirty.push(range.clone());
+            }
+            for (i, slot) in span.vertices.clone().zip(range) {
+                slot_of[i as usize] = slot;
+            }
+        }
+        self.ranges = ranges;
+
+        if grown {
+            log::debug!(
+                "Growing the vertex buffer to {} vertices",
+                self.slots.capacity()
+            );
+            self.vertex_buffer = create_vertex_buffer(device, self.slots.capacity());
+            queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
+        } else {
+            dirty.sort_by_key(|r| r.start);
+            for range in merge(dirty) {
+                let vertices = &self.vertices[range.start as usize..range.end as usize];
+                let offset = range.start as usize * std::mem::size_of::<Vertex>();
+                queue.write_buffer(
+                    &self.vertex_buffer,
+                    offset as wgpu::BufferAddress,
+                    bytemuck::cast_slice(vertices),
+                );
+            }
+        }
+
+        let indices: Vec<u32> = geometry
+            .indices
+            .iter()
+            .map(|&i| slot_of[i as usize])
+            .collect();
+        let format = index_format(self.slots.capacity());
+        if format != self.index_format || indices.len() as u32 > self.index_capacity {
+            self.index_format = format;
+            self.index_capacity = self
+                .index_capacity
+                .max(indices.len() as u32)
+                .next_power_of_two();
+            log::debug!(
+                "Growing the index buffer to {} indices",
+                self.index_capacity
+            );
+            self.index_buffer = create_index_buffer(device, format, self.index_capacity);
+            self.write_indices(queue, &indices, 0..indices.len());
+        } else {
+            let start = indices
+                .iter()
+                .zip(&self.indices)
+                .position(|(a, b)| a != b)
+                .unwrap_or(indices.len().min(self.indices.len()));
+            // Indices past the new end are left as they are, nothing draws
+            // them.
+            let end = if indices.len() == self.indices.len() {
+                let unchanged = indices[start..]
+                    .iter()
+                    .rev()
+                    .zip(self.indices[start..].iter().rev())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                indices.len() - unchanged
+            } else {
+                indices.len()
+            };
+            self.write_indices(queue, &indices, start..end);
+        }
+        self.indices = indices;
+    }
+
+    // Writes `indices[range]` to the index buffer, in the current format.
+    fn write_indices(&self, queue: &wgpu::Queue, indices: &[u32], range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        // Writes start and end on 4 bytes. Widening the range by one 16-bit
+        // index at either end covers that, padding with zero past the last.
+        let mut bytes = Vec::new();
+        let offset = match self.index_format {
+            wgpu::IndexFormat::Uint16 => {
+                let start = range.start & !1;
+                let end = (range.end + 1) & !1;
+                for i in start..end {
+                    let index = indices.get(i).copied().unwrap_or(0) as u16;
+                    bytes.extend_from_slice(&index.to_ne_bytes());
+                }
+                start * 2
+            }
+            wgpu::IndexFormat::Uint32 => {
+                bytes.extend_from_slice(bytemuck::cast_slice(&indices[range.clone()]));
+                range.start * 4
+            }
+        };
+        queue.write_buffer(&self.index_buffer, offset as wgpu::BufferAddress, &bytes);
+    }
+}
+
+/// A buffer of slots of the same size, e.g. instances or paint uniforms.
+/// Like [`GeometryBuffers`], it only writes the slots that changed since the
+/// last upload, and doubles when it runs out.
+pub struct SlotBuffer {
+    label: &'static str,
+    usage: wgpu::BufferUsages,
+    stride: usize,
+    buffer: wgpu::Buffer,
+    // What the buffer holds, `stride` bytes per slot.
+    contents: Vec<u8>,
+}
+
+impl SlotBuffer {
+    /// `stride` has to be a multiple of 4, as buffers are written 4 bytes
+    /// at a time.
+    pub fn new(
+        device: &wgpu::Device,
+        label: &'static str,
+        usage: wgpu::BufferUsages,
+        stride: usize,
+    ) -> Self {
+        let contents = vec![0; INITIAL_SLOTS * stride];
+        Self {
+            label,
+            usage,
+            stride,
+            buffer: create_buffer(device, label, usage, contents.len()),
+            contents,
+        }
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// Makes the buffer hold `slots`, each written at the start of its own
+    /// `stride` bytes and padded with zeros. Returns true if the buffer was
+    /// recreated to make room, so bind groups using it have to be too.
+    pub fn upload<'a>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        slots: impl ExactSizeIterator<Item = &'a [u8]>,
+    ) -> bool {
+        let len = slots.len() * self.stride;
+        let grown = len > self.contents.len();
+        if grown {
+            self.contents
+                .resize((len / self.stride).next_power_of_two() * self.stride, 0);
+        }
+        // Slots past the new end are left as they are, nothing reads them.
+        let mut dirty = Vec::new();
+        for (i, bytes) in slots.enumerate() {
+            let slot = &mut self.contents[i * self.stride..(i + 1) * self.stride];
+            let (start, padding) = slot.split_at_mut(bytes.len());
+            if start != bytes || padding.iter().any(|&b| b != 0) {
+                start.copy_from_slice(bytes);
+                padding.fill(0);
+                dirty.push(i as u32..i as u32 + 1);
+            }
+        }
+        if grown {
+            log::debug!(
+                "Growing the {} to {} slots",
+                self.label,
+                self.contents.len() / self.stride
+            );
+            self.buffer = create_buffer(device, self.label, self.usage, self.contents.len());
+            queue.write_buffer(&self.buffer, 0, &self.contents);
+        } else {
+            for range in merge(dirty) {
+                let bytes = range.start as usize * self.stride..range.end as usize * self.stride;
+                queue.write_buffer(
+                    &self.buffer,
+                    bytes.start as wgpu::BufferAddress,
+                    &self.contents[bytes],
+                );
+            }
+        }
+        grown
+    }
+}
+
+// Joins ranges sorted by their start that are close to each other.
+fn merge(ranges: Vec<Range<u32>>) -> Vec<Range<u32>> {
+    let mut merged: Vec<Range<u32>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end + MERGE_GAP => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+fn index_format(vertex_capacity: u32) -> wgpu::IndexFormat {
+    if vertex_capacity <= 1 << 16 {
+        wgpu::IndexFormat::Uint16
+    } else {
+        wgpu::IndexFormat::Uint32
+    }
+}
+
+fn create_buffer(
+    device: &wgpu::Device,
+    label: &str,
+    usage: wgpu::BufferUsages,
+    size: usize,
+) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size: size as wgpu::BufferAddress,
+        usage: usage | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_vertex_buffer(device: &wgpu::Device, capacity: u32) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Vertex Buffer"),
+        size: capacity as wgpu::BufferAddress
+            * std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_index_buffer(
+    device: &wgpu::Device,
+    format: wgpu::IndexFormat,
+    capacity: u32,
+) -> wgpu::Buffer {
+    let size = match format {
+        wgpu::IndexFormat::Uint16 => 2,
+        wgpu::IndexFormat::Uint32 => 4,
+    };
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Index Buffer"),
+        size: capacity as wgpu::BufferAddress * size,
+        usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
@@ -0,0 +1,209 @@
+//! Maps scene coordinates, in logical pixels with the origin at the top-left
+//! corner and y pointing down, to clip space.
+
+use lyon::math::{point, vector, Point, Transform, Vector};
+use winit::event::*;
+
+const MIN_ZOOM: f32 = 0.01;
+const MAX_ZOOM: f32 = 1000.0;
+
+pub struct Camera {
+    /// Viewport size in logical pixels.
+    pub width: f32,
+    pub height: f32,
+    /// The scene point shown at the top-left corner of the viewport.
+    pub offset: Vector,
+    /// Logical pixels per scene unit.
+    pub zoom: f32,
+}
+
+impl Camera {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            width,
+            height,
+            offset: vector(0.0, 0.0),
+            zoom: 1.0,
+        }
+    }
+
+    /// Orthographic projection from scene coordinates to clip space. Both
+    /// axes share the same scale, so the aspect ratio of shapes doesn't depend
+    /// on the window's.
+    pub fn view_proj(&self) -> Transform {
+        let (width, height) = (self.width.max(1.0), self.height.max(1.0));
+        Transform::translation(-self.offset.x, -self.offset.y)
+            .then_scale(self.zoom, self.zoom)
+            .then_scale(2.0 / width, -2.0 / height)
+            .then_translate(vector(-1.0, 1.0))
+    }
+
+    /// `scale_factor` is the number of device pixels per logical pixel and
+    /// `feather` the distance, in device pixels, edges are pushed out by to
+    /// fade them; zero turns feathering off.
+    pub fn uniform(&self, scale_factor: f32, feather: f32) -> CameraUniform {
+        CameraUniform {
+            // euclid uses row vectors, so its rows are WGSL's columns.
+            view_proj: self.view_proj().to_3d().to_arrays(),
+            pixel_size: 1.0 / (self.zoom * scale_factor),
+            feather,
+            _padding: [0.0; 2],
+        }
+    }
+
+    /// The scene point under `cursor`, given in logical pixels.
+    pub fn to_scene(&self, cursor: Point) -> Point {
+        cursor / self.zoom + self.offset
+    }
+
+    /// Multiplies the zoom by `factor`, keeping the scene point under
+    /// `cursor` where it is.
+    pub fn zoom_around(&mut self, cursor: Point, factor: f32) {
+        let anchor = self.to_scene(cursor);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.offset = anchor.to_vector() - cursor.to_vector() / self.zoom;
+    }
+
+    /// Moves the view by `delta` logical pixels.
+    pub fn pan(&mut self, delta: Vector) {
+        self.offset -= delta / self.zoom;
+    }
+
+    pub fn reset(&mut self) {
+        self.offset = vector(0.0, 0.0);
+        self.zoom = 1.0;
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    // Scene units per device pixel.
+    pixel_size: f32,
+    feather: f32,
+    // Uniform structs are a multiple of 16 bytes.
+    _padding: [f32; 2],
+}
+
+/// Mouse wheel zooms around the cursor, dragging with the left button pans,
+/// the arrow keys pan and `R` resets the view.
+pub struct CameraController {
+    // Logical pixels per frame.
+    speed: f32,
+    cursor: Point,
+    is_dragging: bool,
+    is_left_pressed: bool,
+    is_right_pressed: bool,
+    is_up_pressed: bool,
+    is_down_pressed: bool,
+}
+
+impl CameraController {
+    pub fn new(speed: f32) -> Self {
+        Self {
+            speed,
+            cursor: point(0.0, 0.0),
+            is_dragging: false,
+            is_left_pressed: false,
+            is_right_pressed: false,
+            is_up_pressed: false,
+            is_down_pressed: false,
+        }
+    }
+
+    /// Applies mouse input to `camera` right away. Returns true if the event
+    /// was used.
+    pub fn process_events(
+        &mut self,
+        event: &WindowEvent,
+        camera: &mut Camera,
+        scale_factor: f64,
+    ) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = position.to_logical::<f32>(scale_factor);
+                let cursor = point(position.x, position.y);
+                let delta = cursor - self.cursor;
+                self.cursor = cursor;
+                if self.is_dragging {
+                    camera.pan(delta);
+                }
+                self.is_dragging
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.is_dragging = *state == ElementState::Pressed;
+                true
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let notches = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / 50.0,
+                };
+                camera.zoom_around(self.cursor, 1.1f32.powf(notches));
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => {
+                let is_pressed = *state == ElementState::Pressed;
+                match keycode {
+                    VirtualKeyCode::Left => {
+                        self.is_left_pressed = is_pressed;
+                        true
+                    }
+                    VirtualKeyCode::Right => {
+                        self.is_right_pressed = is_pressed;
+                        true
+                    }
+                    VirtualKeyCode::Up => {
+                        self.is_up_pressed = is_pressed;
+                        true
+                    }
+                    VirtualKeyCode::Down => {
+                        self.is_down_pressed = is_pressed;
+                        true
+                    }
+                    VirtualKeyCode::R => {
+                        if is_pressed {
+                            camera.reset();
+                        }
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Pans `camera` for the arrow keys held down. Called once per frame.
+    pub fn update_camera(&self, camera: &mut Camera) {
+        let mut direction = vector(0.0, 0.0);
+        if self.is_left_pressed {
+            direction.x += 1.0;
+        }
+        if self.is_right_pressed {
+            direction.x -= 1.0;
+        }
+        if self.is_up_pressed {
+            direction.y += 1.0;
+        }
+        if self.is_down_pressed {
+            direction.y -= 1.0;
+        }
+        if direction != vector(0.0, 0.0) {
+            camera.pan(direction * self.speed);
+        }
+    }
+}
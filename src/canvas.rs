@@ -0,0 +1,541 @@
+//! A small subset of the HTML Canvas 2D API that records into a [`Scene`].
+//!
+//! Coordinates are in canvas pixels with the origin at the top-left corner,
+//! like `CanvasRenderingContext2D`, so drawing code can be ported from the JS
+//! prototype line by line.
+
+use std::f32::consts::PI;
+
+use lyon::geom::{Angle, Arc};
+use lyon::math::{point, vector, Point, Transform};
+use lyon::path::{Event as PathEvent, Path};
+use lyon::tessellation::{FillRule, LineCap, LineJoin, StrokeOptions};
+
+use crate::dash::DashPattern;
+use crate::image::Image;
+use crate::paint::{ImagePaint, LinearGradient, Paint, RadialGradient};
+use crate::scene::{Clip, Group, GroupId, ImageId, Scene, Shape};
+
+#[derive(Clone, Debug)]
+struct DrawState {
+    transform: Transform,
+    fill_style: Paint,
+    stroke_style: Paint,
+    global_alpha: f32,
+    line_width: f32,
+    line_cap: LineCap,
+    line_join: LineJoin,
+    miter_limit: f32,
+    // Already doubled if given with an odd length, as in the browser.
+    line_dash: Vec<f32>,
+    line_dash_offset: f32,
+    // The group of the innermost clip, holding everything drawn.
+    group: Option<GroupId>,
+}
+
+impl Default for DrawState {
+    fn default() -> Self {
+        // Same defaults as the browser.
+        Self {
+            transform: Transform::identity(),
+            fill_style: Paint::Solid([0.0, 0.0, 0.0, 1.0]),
+            stroke_style: Paint::Solid([0.0, 0.0, 0.0, 1.0]),
+            global_alpha: 1.0,
+            line_width: 1.0,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter,
+            miter_limit: 10.0,
+            line_dash: Vec::new(),
+            line_dash_offset: 0.0,
+            group: None,
+        }
+    }
+}
+
+// The current path, kept as a list of commands so it can be filled and
+// stroked several times. Points are already in canvas space.
+#[derive(Copy, Clone, Debug)]
+enum PathOp {
+    Begin(Point),
+    Line(Point),
+    Quadratic(Point, Point),
+    Cubic(Point, Point, Point),
+    Close,
+}
+
+pub struct Canvas2d {
+    scene: Scene,
+    state: DrawState,
+    stack: Vec<DrawState>,
+    ops: Vec<PathOp>,
+    // Untransformed, like the point the browser keeps for the next command.
+    current: Option<Point>,
+    subpath_start: Option<Point>,
+}
+
+impl Canvas2d {
+    pub fn new() -> Self {
+        let mut scene = Scene::new();
+        // A canvas is transparent on top of a white page.
+        scene.background = [1.0, 1.0, 1.0, 1.0];
+        Self {
+            scene,
+            state: DrawState::default(),
+            stack: Vec::new(),
+            ops: Vec::new(),
+            current: None,
+            subpath_start: None,
+        }
+    }
+
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    pub fn into_scene(self) -> Scene {
+        self.scene
+    }
+
+    /// Sets the fill to an RGBA color, not premultiplied, a gradient or a
+    /// pattern.
+    pub fn set_fill_style(&mut self, paint: impl Into<Paint>) {
+        self.state.fill_style = paint.into();
+    }
+
+    /// Sets the stroke to an RGBA color, not premultiplied, a gradient or a
+    /// pattern.
+    pub fn set_stroke_style(&mut self, paint: impl Into<Paint>) {
+        self.state.stroke_style = paint.into();
+    }
+
+    /// A gradient along the line from `(x0, y0)` to `(x1, y1)`. As in the
+    /// browser, its coordinates are mapped through the transform current
+    /// when filling or stroking, not the one current now.
+    pub fn create_linear_gradient(&self, x0: f32, y0: f32, x1: f32, y1: f32) -> LinearGradient {
+        LinearGradient::new(point(x0, y0), point(x1, y1))
+    }
+
+    /// A gradient from the circle around `(x0, y0)` to the one around
+    /// `(x1, y1)`. Only a start circle of radius 0 is supported, a larger
+    /// one is treated as a point.
+    pub fn create_radial_gradient(
+        &self,
+        x0: f32,
+        y0: f32,
+        r0: f32,
+        x1: f32,
+        y1: f32,
+        r1: f32,
+    ) -> RadialGradient {
+        if r0 != 0.0 {
+            log::warn!("Radial gradients with a start radius aren't supported");
+        }
+        RadialGradient::new(point(x1, y1), r1).with_focal(point(x0, y0))
+    }
+
+    /// Adds an image to the canvas' scene, for [`Canvas2d::create_pattern`]
+    /// and [`Canvas2d::draw_image`].
+    pub fn add_image(&mut self, image: Image) -> ImageId {
+        self.scene.add_image(image)
+    }
+
+    /// Paints with `image`, its top left corner at the origin and tiled in
+    /// both directions if `repeat` is set. Like gradients, it is mapped
+    /// through the transform current when filling or stroking.
+    pub fn create_pattern(&self, image: ImageId, repeat: bool) -> ImagePaint {
+        ImagePaint::new(image).with_repeat(repeat)
+    }
+
+    /// Draws `image` stretched over the rectangle at `(x, y)`.
+    pub fn draw_image(&mut self, image: ImageId, x: f32, y: f32, width: f32, height: f32) {
+        let Some(size) = self.scene.image(image).map(|i| (i.width, i.height)) else {
+            log::warn!("Can't draw an image not added to the canvas: {:?}", image);
+            return;
+        };
+        let to_rect = Transform::scale(width / size.0 as f32, height / size.1 as f32)
+            .then_translate(vector(x, y))
+            .then(&self.state.transform);
+        let paint = ImagePaint::new(image).with_transform(to_rect);
+        let path = rect_path(x, y, width, height).transformed(&self.state.transform);
+        self.add_shape(Shape::new(path).with_fill(paint));
+    }
+
+    /// Opacity applied to everything drawn from now on.
+    pub fn set_global_alpha(&mut self, alpha: f32) {
+        // Out of range values are ignored, as in the browser.
+        if (0.0..=1.0).contains(&alpha) {
+            self.state.global_alpha = alpha;
+        }
+    }
+
+    pub fn set_line_width(&mut self, width: f32) {
+        // Like the browser, invalid widths are ignored.
+        if width.is_finite() && width > 0.0 {
+            self.state.line_width = width;
+        }
+    }
+
+    pub fn set_line_cap(&mut self, cap: LineCap) {
+        self.state.line_cap = cap;
+    }
+
+    pub fn set_line_join(&mut self, join: LineJoin) {
+        self.state.line_join = join;
+    }
+
+    pub fn set_miter_limit(&mut self, limit: f32) {
+        if limit.is_finite() && limit > 0.0 {
+            self.state.miter_limit = limit;
+        }
+    }
+
+    /// Dashes strokes with alternating dash and gap lengths. An empty list
+    /// makes them solid again; one with negative or non-finite lengths is
+    /// ignored.
+    pub fn set_line_dash(&mut self, segments: &[f32]) {
+        if segments.iter().any(|s| !s.is_finite() || *s < 0.0) {
+            return;
+        }
+        self.state.line_dash = segments.to_vec();
+        if segments.len() % 2 == 1 {
+            self.state.line_dash.extend_from_slice(segments);
+        }
+    }
+
+    pub fn line_dash(&self) -> &[f32] {
+        &self.state.line_dash
+    }
+
+    /// How far into the dash pattern strokes start.
+    pub fn set_line_dash_offset(&mut self, offset: f32) {
+        if offset.is_finite() {
+            self.state.line_dash_offset = offset;
+        }
+    }
+
+    pub fn save(&mut self) {
+        self.stack.push(self.state.clone());
+    }
+
+    pub fn restore(&mut self) {
+        if let Some(state) = self.stack.pop() {
+            self.state = state;
+        }
+    }
+
+    pub fn translate(&mut self, x: f32, y: f32) {
+        self.state.transform = self.state.transform.pre_translate(vector(x, y));
+    }
+
+    pub fn scale(&mut self, x: f32, y: f32) {
+        self.state.transform = self.state.transform.pre_scale(x, y);
+    }
+
+    /// Rotates by `angle` radians, clockwise on screen.
+    pub fn rotate(&mut self, angle: f32) {
+        self.state.transform = self.state.transform.pre_rotate(Angle::radians(angle));
+    }
+
+    pub fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        let path = rect_path(x, y, width, height).transformed(&self.state.transform);
+        self.fill_path(path, FillRule::NonZero);
+    }
+
+    pub fn stroke_rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        self.stroke_path(rect_path(x, y, width, height));
+    }
+
+    pub fn begin_path(&mut self) {
+        self.ops.clear();
+        self.current = None;
+        self.subpath_start = None;
+    }
+
+    pub fn move_to(&mut self, x: f32, y: f32) {
+        let p = point(x, y);
+        self.ops.push(PathOp::Begin(self.to_canvas(p)));
+        self.current = Some(p);
+        self.subpath_start = Some(p);
+    }
+
+    pub fn line_to(&mut self, x: f32, y: f32) {
+        if self.ensure_subpath(point(x, y)) {
+            return;
+        }
+        let p = point(x, y);
+        self.ops.push(PathOp::Line(self.to_canvas(p)));
+        self.current = Some(p);
+    }
+
+    pub fn quadratic_curve_to(&mut self, cpx: f32, cpy: f32, x: f32, y: f32) {
+        self.ensure_subpath(point(cpx, cpy));
+        let p = point(x, y);
+        self.ops.push(PathOp::Quadratic(
+            self.to_canvas(point(cpx, cpy)),
+            self.to_canvas(p),
+        ));
+        self.current = Some(p);
+    }
+
+    pub fn bezier_curve_to(&mut self, cp1x: f32, cp1y: f32, cp2x: f32, cp2y: f32, x: f32, y: f32) {
+        self.ensure_subpath(point(cp1x, cp1y));
+        let p = point(x, y);
+        self.ops.push(PathOp::Cubic(
+            self.to_canvas(point(cp1x, cp1y)),
+            self.to_canvas(point(cp2x, cp2y)),
+            self.to_canvas(p),
+        ));
+        self.current = Some(p);
+    }
+
+    /// Adds a circular arc around `(x, y)`. Angles are in radians and, as in
+    /// the browser, a line is drawn from the current point to the start of
+    /// the arc.
+    pub fn arc(
+        &mut self,
+        x: f32,
+        y: f32,
+        radius: f32,
+        start_angle: f32,
+        end_angle: f32,
+        anticlockwise: bool,
+    ) {
+        let sweep = arc_sweep(start_angle, end_angle, anticlockwise);
+        let arc = Arc {
+            center: point(x, y),
+            radii: vector(radius, radius),
+            start_angle: Angle::radians(start_angle),
+            sweep_angle: Angle::radians(sweep),
+            x_rotation: Angle::zero(),
+        };
+        let start = arc.from();
+        if self.current.is_some() {
+            self.line_to(start.x, start.y);
+        } else {
+            self.move_to(start.x, start.y);
+        }
+        let transform = self.state.transform;
+        let ops = &mut self.ops;
+        arc.for_each_cubic_bezier(&mut |segment| {
+            ops.push(PathOp::Cubic(
+                transform.transform_point(segment.ctrl1),
+                transform.transform_point(segment.ctrl2),
+                transform.transform_point(segment.to),
+            ));
+        });
+        self.current = Some(arc.to());
+    }
+
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        self.move_to(x, y);
+        self.line_to(x + width, y);
+        self.line_to(x + width, y + height);
+        self.line_to(x, y + height);
+        self.close_path();
+    }
+
+    pub fn close_path(&mut self) {
+        if let Some(start) = self.subpath_start {
+            self.ops.push(PathOp::Close);
+            self.current = Some(start);
+        }
+    }
+
+    /// Appends every sub-path of `path` to the current path, mapped through
+    /// the current transform. The equivalent of `Path2D.addPath`.
+    pub fn add_path(&mut self, path: &Path) {
+        for event in path.iter() {
+            match event {
+                PathEvent::Begin { at } => self.move_to(at.x, at.y),
+                PathEvent::Line { to, .. } => self.line_to(to.x, to.y),
+                PathEvent::Quadratic { ctrl, to, .. } => {
+                    self.quadratic_curve_to(ctrl.x, ctrl.y, to.x, to.y)
+                }
+                PathEvent::Cubic {
+                    ctrl1, ctrl2, to, ..
+                } => self.bezier_curve_to(ctrl1.x, ctrl1.y, ctrl2.x, ctrl2.y, to.x, to.y),
+                PathEvent::End { close: true, .. } => self.close_path(),
+                PathEvent::End { close: false, .. } => {}
+            }
+        }
+    }
+
+    /// Fills the current path with the fill style.
+    pub fn fill(&mut self) {
+        self.fill_with_rule(FillRule::NonZero);
+    }
+
+    /// Fills the current path with the fill style, deciding what is inside
+    /// with `rule`, like `fill("evenodd")` in the browser.
+    pub fn fill_with_rule(&mut self, rule: FillRule) {
+        let path = self.build_path();
+        self.fill_path(path, rule);
+    }
+
+    /// Strokes the current path with the stroke style.
+    pub fn stroke(&mut self) {
+        // Like the browser, nothing is stroked under a transform that
+        // flattens everything.
+        let Some(inverse) = self.state.transform.inverse() else {
+            return;
+        };
+        let path = self.build_path().transformed(&inverse);
+        self.stroke_path(path);
+    }
+
+    /// Restricts everything drawn from now on to the inside of the current
+    /// path, on top of any clip already set. Like the other drawing state,
+    /// [`Canvas2d::restore`] takes it back off.
+    pub fn clip(&mut self) {
+        self.clip_with_rule(FillRule::NonZero);
+    }
+
+    /// [`Canvas2d::clip`] deciding what is inside with `rule`, like
+    /// `clip("evenodd")` in the browser.
+    pub fn clip_with_rule(&mut self, rule: FillRule) {
+        let clip = Clip::new(self.build_path()).with_fill_rule(rule);
+        let mut group = Group::new().with_clip(clip);
+        group.parent = self.state.group;
+        self.state.group = Some(self.scene.add_group(group));
+    }
+
+    fn fill_path(&mut self, path: Path, rule: FillRule) {
+        // Paths are already in canvas space, paints not yet.
+        let paint = self.state.fill_style.transformed(&self.state.transform);
+        self.add_shape(Shape::new(path).with_fill(paint).with_fill_rule(rule));
+    }
+
+    // Unlike fills, `path` is in user space: the stroke is outlined there
+    // and then transformed, so a non-uniform scale or a skew stretches the
+    // line width and dashes as in the browser.
+    fn stroke_path(&mut self, path: Path) {
+        let options = StrokeOptions::default()
+            .with_line_width(self.state.line_width)
+            .with_line_cap(self.state.line_cap)
+            .with_line_join(self.state.line_join)
+            .with_miter_limit(self.state.miter_limit);
+        let mut shape = Shape::new(path)
+            .with_stroke(self.state.stroke_style.clone(), options)
+            .with_transform(self.state.transform);
+        if !self.state.line_dash.is_empty() {
+            let dash =
+                DashPattern::new(&self.state.line_dash).with_offset(self.state.line_dash_offset);
+            shape = shape.with_dash(dash);
+        }
+        self.add_shape(shape);
+    }
+
+    fn add_shape(&mut self, mut shape: Shape) {
+        shape.opacity = self.state.global_alpha;
+        shape.group = self.state.group;
+        self.scene.add(shape);
+    }
+
+    fn to_canvas(&self, p: Point) -> Point {
+        self.state.transform.transform_point(p)
+    }
+
+    // Starts a sub-path at `p` when there is no current point. Returns true
+    // if it did.
+    fn ensure_subpath(&mut self, p: Point) -> bool {
+        if self.current.is_none() {
+            self.move_to(p.x, p.y);
+            return true;
+        }
+        false
+    }
+
+    fn build_path(&self) -> Path {
+        let mut builder = Path::builder();
+        let mut open = false;
+        let mut start = point(0.0, 0.0);
+        for op in &self.ops {
+            // Drawing after close_path continues from where the closed
+            // sub-path started.
+            if !open && !matches!(op, PathOp::Begin(_) | PathOp::Close) {
+                builder.begin(start);
+                open = true;
+            }
+            match *op {
+                PathOp::Begin(p) => {
+                    if open {
+                        builder.end(false);
+                    }
+                    builder.begin(p);
+                    start = p;
+                    open = true;
+                }
+                PathOp::Line(p) => {
+                    builder.line_to(p);
+                }
+                PathOp::Quadratic(ctrl, to) => {
+                    builder.quadratic_bezier_to(ctrl, to);
+                }
+                PathOp::Cubic(ctrl1, ctrl2, to) => {
+                    builder.cubic_bezier_to(ctrl1, ctrl2, to);
+                }
+                PathOp::Close => {
+                    if open {
+                        builder.close();
+                        open = false;
+                    }
+                }
+            }
+        }
+        if open {
+            builder.end(false);
+        }
+        builder.build()
+    }
+}
+
+impl Default for Canvas2d {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn rect_path(x: f32, y: f32, width: f32, height: f32) -> Path {
+    let mut builder = Path::builder();
+    builder.begin(point(x, y));
+    builder.line_to(point(x + width, y));
+    builder.line_to(point(x + width, y + height));
+    builder.line_to(point(x, y + height));
+    builder.close();
+    builder.build()
+}
+
+// How far an arc turns, following the rules of `CanvasRenderingContext2D.arc`.
+fn arc_sweep(start: f32, end: f32, anticlockwise: bool) -> f32 {
+    let tau = 2.0 * PI;
+    if anticlockwise {
+        if start - end >= tau {
+            -tau
+        } else {
+            -(start - end).rem_euclid(tau)
+        }
+    } else if end - start >= tau {
+        tau
+    } else {
+        (end - start).rem_euclid(tau)
+    }
+}
+
+/// Parses a CSS hex color (`#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`) into
+/// RGBA.
+pub fn parse_color(color: &str) -> Option<[f32; 4]> {
+    let hex = color.strip_prefix('#').filter(|hex| hex.is_ascii())?;
+    let digits = match hex.len() {
+        3 | 4 => 1,
+        6 | 8 => 2,
+        _ => return None,
+    };
+    let mut rgba = [1.0; 4];
+    for (i, channel) in hex.as_bytes().chunks(digits).enumerate() {
+        let value = u8::from_str_radix(std::str::from_utf8(channel).ok()?, 16).ok()?;
+        // A single digit is repeated: `#f80` is `#ff8800`.
+        let value = if digits == 1 { value * 17 } else { value };
+        rgba[i] = value as f32 / 255.0;
+    }
+    Some(rgba)
+}
@@ -0,0 +1,173 @@
+//! Splitting paths into dashes, for dashed and dotted strokes.
+
+use lyon::algorithms::measure::{PathMeasurements, SampleType};
+use lyon::path::path::Builder;
+use lyon::path::{Event as PathEvent, Path};
+
+// Dashing a path into more pieces than this is almost certainly a mistake,
+// like a pattern in the wrong units, and would take forever to tessellate.
+const MAX_DASHES: f32 = 100_000.0;
+
+/// Alternating lengths of dashes and gaps along a stroke, as with
+/// `setLineDash` and `lineDashOffset` in the Canvas API.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DashPattern {
+    /// Dash, gap, dash, gap and so on, in path units. An odd number of
+    /// lengths is repeated once to make it even.
+    pub dashes: Vec<f32>,
+    /// How far into the pattern the stroke starts.
+    pub offset: f32,
+}
+
+impl DashPattern {
+    pub fn new(dashes: &[f32]) -> Self {
+        Self {
+            dashes: dashes.to_vec(),
+            offset: 0.0,
+        }
+    }
+
+    pub fn with_offset(mut self, offset: f32) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    // The lengths of one full period of dashes and gaps, or None if the
+    // stroke should be solid.
+    fn period(&self) -> Option<Vec<f32>> {
+        if self.dashes.iter().any(|d| !d.is_finite() || *d < 0.0) {
+            log::warn!(
+                "Ignoring dash pattern with invalid lengths {:?}",
+                self.dashes
+            );
+            return None;
+        }
+        let mut period = self.dashes.clone();
+        if period.len() % 2 == 1 {
+            period.extend_from_slice(&self.dashes);
+        }
+        // Nothing but zeros draws as a solid line, as in SVG.
+        if period.iter().sum::<f32>() <= 0.0 {
+            return None;
+        }
+        Some(period)
+    }
+}
+
+/// The dashes `pattern` cuts `path` into, each one an open sub-path. Curves
+/// stay curves; they are only flattened within `tolerance` to measure them.
+///
+/// The pattern starts over at every sub-path. On a closed one, a dash going
+/// through its start point is kept in one piece, so it gets a join rather
+/// than two caps there. Zero-length dashes are kept as single points, which
+/// round and square caps turn into dots.
+pub fn dash(path: &Path, pattern: &DashPattern, tolerance: f32) -> Path {
+    let Some(period) = pattern.period() else {
+        return path.clone();
+    };
+    let period_length: f32 = period.iter().sum();
+    let offset = pattern.offset.rem_euclid(period_length);
+
+    let mut builder = Path::builder();
+    let mut measurements = PathMeasurements::empty();
+    for (subpath, closed) in subpaths(path) {
+        measurements.initialize_with_path(&subpath, tolerance);
+        let length = measurements.length();
+        if length <= 0.0 {
+            append(&mut builder, &subpath, &Path::new());
+            continue;
+        }
+        if (length + offset) / period_length * period.len() as f32 > MAX_DASHES {
+            log::warn!(
+                "Dash pattern {:?} is too fine for a path {} long, drawing it solid",
+                pattern.dashes,
+                length
+            );
+            return path.clone();
+        }
+        let mut sampler = measurements.create_sampler(&subpath, SampleType::Distance);
+
+        // Dashes as distances along the sub-path, starting `offset` before
+        // it so the pattern lines up.
+        let mut dashes = Vec::new();
+        let mut distance = -offset;
+        for (i, &gap_or_dash) in period.iter().cycle().enumerate() {
+            if distance > length {
+                break;
+            }
+            let range = distance.max(0.0)..(distance + gap_or_dash).min(length);
+            // Dots count, dashes cut down to nothing at either end don't.
+            let is_dot = gap_or_dash == 0.0 && distance >= 0.0;
+            if i % 2 == 0 && (!range.is_empty() || is_dot) {
+                dashes.push(range);
+            }
+            distance += gap_or_dash;
+        }
+
+        // Joined to the first dash through the start of a closed sub-path.
+        let mut wrap = None;
+        if closed && dashes.len() > 1 {
+            let first = &dashes[0];
+            let last = &dashes[dashes.len() - 1];
+            if first.start == 0.0 && last.end == length && first.end > 0.0 && last.start < length {
+                wrap = Some(dashes.remove(0).end);
+            }
+        }
+
+        let last = dashes.len().saturating_sub(1);
+        for (i, range) in dashes.into_iter().enumerate() {
+            if range.is_empty() {
+                // lyon only caps a point with a segment to itself.
+                let at = sampler.sample(range.start).position();
+                builder.begin(at);
+                builder.line_to(at);
+                builder.end(false);
+                continue;
+            }
+            let mut piece = Path::builder();
+            sampler.split_range(range, &mut piece);
+            match wrap.filter(|_| i == last) {
+                Some(end) => {
+                    let mut head = Path::builder();
+                    sampler.split_range(0.0..end, &mut head);
+                    append(&mut builder, &piece.build(), &head.build());
+                }
+                None => append(&mut builder, &piece.build(), &Path::new()),
+            }
+        }
+    }
+    builder.build()
+}
+
+// Each sub-path of `path` on its own, and whether it is closed.
+fn subpaths(path: &Path) -> Vec<(Path, bool)> {
+    let mut subpaths = Vec::new();
+    let mut builder = Path::builder();
+    for event in path.iter() {
+        if let PathEvent::End { close, .. } = event {
+            builder.path_event(event);
+            let subpath = std::mem::replace(&mut builder, Path::builder()).build();
+            subpaths.push((subpath, close));
+        } else {
+            builder.path_event(event);
+        }
+    }
+    subpaths
+}
+
+// Adds `piece` to `builder` as one open sub-path, continued by the segments
+// of `continuation`.
+fn append(builder: &mut Builder, piece: &Path, continuation: &Path) {
+    let events = piece
+        .iter()
+        .filter(|e| !matches!(e, PathEvent::End { .. }))
+        .chain(
+            continuation
+                .iter()
+                .filter(|e| !matches!(e, PathEvent::Begin { .. } | PathEvent::End { .. })),
+        );
+    for event in events {
+        builder.path_event(event);
+    }
+    builder.end(false);
+}
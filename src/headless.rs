@@ -0,0 +1,257 @@
+//! Rendering without a window: frames are drawn into an offscreen texture and
+//! copied back to memory, where they can be saved as PNG.
+
+use std::fmt;
+use std::iter;
+use std::num::NonZeroU32;
+
+use crate::camera::Camera;
+use crate::image::Image;
+use crate::renderer::{supported_sample_count, RenderOptions, Renderer, UnsupportedSampleCount};
+use crate::scene::Scene;
+
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+#[derive(Clone, Debug, Default)]
+pub struct HeadlessOptions {
+    /// Ask for a software adapter (e.g. llvmpipe or WARP), for machines
+    /// without a GPU.
+    pub force_fallback_adapter: bool,
+    pub render: RenderOptions,
+}
+
+#[derive(Debug)]
+pub enum HeadlessError {
+    /// Images have to be at least 1x1 and fit in a texture.
+    InvalidSize {
+        width: u32,
+        height: u32,
+    },
+    NoAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+    UnsupportedSampleCount(UnsupportedSampleCount),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeadlessError::InvalidSize { width, height } => {
+                write!(f, "can't render {}x{} images", width, height)
+            }
+            HeadlessError::NoAdapter => write!(f, "no suitable graphics adapter found"),
+            HeadlessError::RequestDevice(e) => write!(f, "failed to request a device: {}", e),
+            HeadlessError::UnsupportedSampleCount(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+pub struct HeadlessRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    width: u32,
+    height: u32,
+    texture: wgpu::Texture,
+    texture_view: wgpu::TextureView,
+    // Rows in a texture-to-buffer copy have to be aligned, so this can be
+    // wider than the image.
+    padded_bytes_per_row: u32,
+    output_buffer: wgpu::Buffer,
+    renderer: Renderer,
+}
+
+impl HeadlessRenderer {
+    /// Creates a renderer producing `width` by `height` images. The backend
+    /// can be picked with the `WGPU_BACKEND` environment variable.
+    pub async fn new(
+        width: u32,
+        height: u32,
+        options: &HeadlessOptions,
+    ) -> Result<Self, HeadlessError> {
+        let invalid_size = HeadlessError::InvalidSize { width, height };
+        if width == 0 || height == 0 {
+            return Err(invalid_size);
+        }
+        let backends = wgpu::util::backend_bits_from_env().unwrap_or_else(wgpu::Backends::all);
+        let instance = wgpu::Instance::new(backends);
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter: options.force_fallback_adapter,
+            })
+            .await
+            .ok_or(HeadlessError::NoAdapter)?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    features: wgpu::Features::empty(),
+                    // Software adapters often can't do the full defaults.
+                    limits: wgpu::Limits::downlevel_webgl2_defaults()
+                        .using_resolution(adapter.limits()),
+                    label: None,
+                },
+                None,
+            )
+            .await
+            .map_err(HeadlessError::RequestDevice)?;
+        let max = device.limits().max_texture_dimension_2d;
+        if width > max || height > max {
+            return Err(invalid_size);
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Headless Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        });
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = (width * 4).div_ceil(align) * align;
+        let output_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Headless Output Buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let sample_count = supported_sample_count(&adapter, FORMAT, options.render.sample_count)
+            .map_err(HeadlessError::UnsupportedSampleCount)?;
+        let mut renderer = Renderer::new(&device, &queue, FORMAT, sample_count, width, height);
+        renderer.set_feathering(options.render.feathering);
+
+        Ok(Self {
+            device,
+            queue,
+            width,
+            height,
+            texture,
+            texture_view,
+            padded_bytes_per_row,
+            output_buffer,
+            renderer,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// A camera showing scene coordinates `0..width` and `0..height`.
+    pub fn default_camera(&self) -> Camera {
+        Camera::new(self.width as f32, self.height as f32)
+    }
+
+    /// Renders `scene` as seen by `camera` and waits for the result. One
+    /// logical pixel is one pixel of the image. Like any [`Image`], its
+    /// alpha is not premultiplied, so it can be saved as PNG as it is.
+    pub fn render(&mut self, scene: &Scene, camera: &Camera) -> Image {
+        self.renderer
+            .prepare(&self.device, &self.queue, scene, camera, 1.0);
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Headless Encoder"),
+            });
+        self.renderer
+            .render(&mut encoder, &self.texture_view, scene.background);
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.output_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(self.padded_bytes_per_row),
+                    rows_per_image: NonZeroU32::new(self.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.queue.submit(iter::once(encoder.finish()));
+
+        let slice = self.output_buffer.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            tx.send(result).unwrap();
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        rx.recv()
+            .unwrap()
+            .expect("Failed to map the headless output buffer");
+
+        let mut pixels = Vec::with_capacity((self.width * self.height * 4) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..(self.width * 4) as usize]);
+            }
+        }
+        self.output_buffer.unmap();
+        for pixel in pixels.chunks_exact_mut(4) {
+            unpremultiply(pixel);
+        }
+
+        Image {
+            width: self.width,
+            height: self.height,
+            pixels,
+        }
+    }
+}
+
+// The texture holds premultiplied colors, sRGB-encoded after blending in
+// linear space. Divides by alpha in linear space too.
+fn unpremultiply(pixel: &mut [u8]) {
+    match pixel[3] {
+        255 => {}
+        0 => pixel[..3].fill(0),
+        alpha => {
+            let alpha = alpha as f32 / 255.0;
+            for c in &mut pixel[..3] {
+                let linear = srgb_to_linear(*c as f32 / 255.0) / alpha;
+                *c = (linear_to_srgb(linear.min(1.0)) * 255.0).round() as u8;
+            }
+        }
+    }
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
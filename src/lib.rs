@@ -1,41 +1,43 @@
 use std::iter;
 
-use lyon::lyon_tessellation::{BuffersBuilder, FillVertex, StrokeOptions, StrokeVertex};
-use lyon::math::{point, Box2D, Point};
-use lyon::path::{builder::BorderRadii, Event as PathEvent, Path, PathSlice, Winding};
-use lyon::tessellation::geometry_builder::simple_builder;
-use lyon::tessellation::{FillOptions, FillTessellator, StrokeTessellator, VertexBuffers};
-
-// use lyon::geom::{CubicBezierSegment, Point};
-use wgpu::util::DeviceExt;
+use lyon::tessellation::{LineCap, LineJoin};
+
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
     window::{Window, WindowBuilder},
 };
 
-use log::{debug, info, Level};
+use log::{debug, info};
+
+mod batch;
+mod buffers;
+pub mod camera;
+pub mod canvas;
+pub mod dash;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod headless;
+pub mod image;
+pub mod paint;
+mod renderer;
+pub mod scene;
+pub mod shapes;
+pub mod svg;
+mod tessellate;
+
+use camera::{Camera, CameraController};
+use canvas::{parse_color, Canvas2d};
+use renderer::{supported_sample_count, Renderer};
+pub use renderer::{RenderOptions, UnsupportedSampleCount};
+use scene::Scene;
+use shapes::arrow;
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
-const VERTICES: &[Vertex] = &[
-    // Vertex {
-    //     position: [0.0, 0.5, 0.0],
-    //     color: [1.0, 0.0, 0.0],
-    // },
-    Vertex {
-        position: [-1.0, 1.0, 0.0],
-        color: [0.0, 1.0, 0.0],
-    },
-    Vertex {
-        position: [0.5, -0.5, 0.0],
-        color: [0.0, 0.0, 1.0],
-    },
-];
-
 // #[cfg_attr(target_arch = "wasm32", wasm_bindgen(module = "../defined-in-js.js"))]
-#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
 extern "C" {
     fn hello_world() -> String;
     fn get_window_width() -> u32;
@@ -46,7 +48,18 @@ extern "C" {
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 struct Vertex {
     position: [f32; 3],
-    color: [f32; 3],
+    // Premultiplied RGBA.
+    color: [f32; 4],
+    // The rest is only used by edge feathering. Outward direction along
+    // which the vertex is pushed, scaled like lyon's stroke normals; zero
+    // inside fills.
+    normal: [f32; 2],
+    // -1 or 1 depending on the side of the stroke the vertex is on.
+    side: f32,
+    // Half the stroke width, in scene units.
+    half_width: f32,
+    // Position in the shape's path space, where paints are defined.
+    local: [f32; 2],
 }
 
 impl Vertex {
@@ -63,7 +76,75 @@ impl Vertex {
                 wgpu::VertexAttribute {
                     offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
                     shader_location: 1,
-                    format: wgpu::VertexFormat::Float32x3,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 7]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 9]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 10]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 11]>() as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+            ],
+        }
+    }
+}
+
+// A copy of some geometry, drawn with instancing. Geometry drawn only once
+// uses the identity instance.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct InstanceRaw {
+    // Columns of the 2x3 matrix applied to scene positions.
+    transform: [[f32; 2]; 3],
+    // Premultiplied RGBA multiplying the vertex color.
+    tint: [f32; 4],
+}
+
+impl InstanceRaw {
+    const IDENTITY: InstanceRaw = InstanceRaw {
+        transform: [[1.0, 0.0], [0.0, 1.0], [0.0, 0.0]],
+        tint: [1.0; 4],
+    };
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
+            // Advances once per instance rather than per vertex.
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 8,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                    shader_location: 9,
+                    format: wgpu::VertexFormat::Float32x4,
                 },
             ],
         }
@@ -73,9 +154,10 @@ impl Vertex {
 fn init_logger() {
     cfg_if::cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
-            console_log::init_with_level(Level::Debug);
+            console_log::init_with_level(log::Level::Debug);
         } else {
-            env_logger::init();
+            // Examples may have set it up already.
+            let _ = env_logger::try_init();
             println!("Hello, world!");
         }
     }
@@ -84,8 +166,8 @@ fn init_logger() {
 }
 
 // #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[cfg(target_arch = "wasm32")]
 fn create_canvas(window: &Window, width: u32, height: u32) {
-    #[cfg(target_arch = "wasm32")]
     {
         // Winit prevents sizing with CSS, so we have to set
         // the size manually when on web.
@@ -105,224 +187,82 @@ fn create_canvas(window: &Window, width: u32, height: u32) {
     }
 }
 
+// The scene sketched with the Canvas 2D API in index.html.
+fn default_scene() -> Scene {
+    let mut ctx = Canvas2d::new();
+
+    // Big green rectangle.
+    ctx.set_fill_style(parse_color("#2a9d8f").unwrap());
+    ctx.fill_rect(500.0, 250.0, 200.0, 100.0);
+
+    // Line.
+    ctx.begin_path();
+    ctx.move_to(700.0, 350.0);
+    ctx.line_to(1000.0, 350.0);
+    ctx.stroke();
+
+    // Small light blue rectagle.
+    ctx.set_fill_style(parse_color("#a2d2ff").unwrap());
+    ctx.fill_rect(1000.0, 350.0, 100.0, 50.0);
+
+    // Middle size yellow rectangle.
+    ctx.set_fill_style(parse_color("#e9c46a").unwrap());
+    ctx.fill_rect(775.0, 300.0, 150.0, 75.0);
+
+    // The arrow, filled and outlined.
+    ctx.save();
+    ctx.translate(250.0, 300.0);
+    ctx.scale(100.0, 100.0);
+    ctx.begin_path();
+    ctx.add_path(&arrow());
+    ctx.set_fill_style([1.0, 1.0, 0.0, 1.0]);
+    ctx.fill();
+    ctx.set_stroke_style([1.0, 0.5, 0.0, 1.0]);
+    ctx.set_line_width(0.1);
+    ctx.set_line_cap(LineCap::Round);
+    ctx.set_line_join(LineJoin::Round);
+    ctx.stroke();
+    ctx.restore();
+
+    // A Bézier ribbon, filled and outlined.
+    ctx.save();
+    ctx.translate(250.0, 500.0);
+    ctx.scale(100.0, 100.0);
+    ctx.begin_path();
+    ctx.move_to(0.0, 0.0);
+    ctx.bezier_curve_to(0.5, -0.8, 1.0, -1.3, 2.0, 1.0);
+    ctx.line_to(2.0, 1.1);
+    ctx.bezier_curve_to(1.0, -1.2, 0.5, -0.7, 0.0, 0.1);
+    ctx.close_path();
+    ctx.set_fill_style([1.0, 0.0, 0.0, 1.0]);
+    ctx.fill();
+    ctx.set_line_width(0.02);
+    ctx.stroke();
+    ctx.restore();
+
+    ctx.into_scene()
+}
+
 struct State {
     surface: wgpu::Surface,
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
     size: winit::dpi::PhysicalSize<u32>,
-    render_pipeline: wgpu::RenderPipeline,
-    vertex_buffer: wgpu::Buffer,
-    num_vertices: u32,
-    // index_buffer: wgpu::Buffer,
-    // num_indices: u32,
+    renderer: Renderer,
+    scene: Scene,
+    scale_factor: f64,
+    camera: Camera,
+    camera_controller: CameraController,
 }
 
 impl State {
     // Creating some of the wgpu types requires async code
-    async fn new(window: &Window) -> Self {
-        // let cb_curve = CubicBezierSegment {
-        //     from: Point {
-        //         x: VERTICES[0].position[0],
-        //         y: VERTICES[0].position[1],
-        //         ..Default::default()
-        //     },
-        //     ctrl1: Point {
-        //         x: VERTICES[0].position[0] + 0.3,
-        //         y: VERTICES[0].position[1] - 0.5,
-        //         ..Default::default()
-        //     },
-        //     ctrl2: Point {
-        //         x: VERTICES[0].position[0] + 0.4,
-        //         y: VERTICES[0].position[1] + 0.8,
-        //         ..Default::default()
-        //     },
-        //     to: Point {
-        //         x: VERTICES[1].position[0],
-        //         y: VERTICES[1].position[1],
-        //         ..Default::default()
-        //     },
-        // };
-
-        // let flattened = cb_curve.flattened(0.002);
-        // // while let Some(p) = flattened.next() {
-        // //     eprintln!("{:?}", p);
-        // // }
-
-        // let vertices = flattened
-        //     .into_iter()
-        //     .map(|p| Vertex {
-        //         position: [p.x, p.y, 0.0],
-        //         color: [1.0, 0.0, 0.0],
-        //     })
-        //     .collect::<Vec<Vertex>>();
-
-        // // Example 2
-        // let mut geometry: VertexBuffers<Point, u16> = VertexBuffers::new();
-        // let mut geometry_builder = simple_builder(&mut geometry);
-        // let options = FillOptions::tolerance(0.1);
-        // let mut tessellator = FillTessellator::new();
-
-        // let mut builder = tessellator.builder(&options, &mut geometry_builder);
-
-        // builder.add_rounded_rectangle(
-        //     &Box2D {
-        //         min: point(0.0, 0.0),
-        //         max: point(100.0, 1.0),
-        //     },
-        //     &BorderRadii {
-        //         top_left: 0.0,
-        //         top_right: 0.0,
-        //         bottom_left: 0.0,
-        //         bottom_right: 0.0,
-        //     },
-        //     Winding::Positive,
-        // );
-
-        // let _ = builder.build();
-
-        // // The tessellated geometry is ready to be uploaded to the GPU.
-        // println!(
-        //     " -- {} vertices {} indices",
-        //     geometry.vertices.len(),
-        //     geometry.indices.len()
-        // );
-
-        // // Create a simple path.
-        // let mut path_builder = Path::builder();
-        // path_builder.begin(point(0.0, 0.0));
-        // path_builder.line_to(point(1.0, 2.0));
-        // path_builder.line_to(point(2.0, 0.0));
-        // path_builder.line_to(point(1.0, 1.0));
-        // path_builder.end(true);
-        // let path = path_builder.build();
-
-        // // Create the destination vertex and index buffers.
-        // let mut buffers: VertexBuffers<Point, u16> = VertexBuffers::new();
-
-        // {
-        //     // Create the destination vertex and index buffers.
-        //     let mut vertex_builder = simple_builder(&mut buffers);
-
-        //     // Create the tessellator.
-        //     let mut tessellator = StrokeTessellator::new();
-
-        //     // Compute the tessellation.
-        //     let _ = tessellator.tessellate(&path, &StrokeOptions::default(), &mut vertex_builder);
-        // }
-
-        // println!("The generated vertices are: {:?}.", &buffers.vertices[..]);
-        // println!("The generated indices are: {:?}.", &buffers.indices[..]);
-
-        let mut geometry: VertexBuffers<Vertex, u16> = VertexBuffers::new();
-        let mut fill_tess = FillTessellator::new();
-
-        // Build a Path for the arrow.
-        let mut builder = Path::builder();
-        builder.begin(point(-1.0, -0.2));
-        builder.line_to(point(0.5, -0.2));
-        builder.line_to(point(0.5, -0.7));
-        builder.line_to(point(1.5, 0.0));
-        builder.line_to(point(0.5, 0.7));
-        builder.line_to(point(0.5, 0.2));
-        builder.line_to(point(-1.0, 0.2));
-        builder.close();
-        let path = builder.build();
-
-        let mut vertices: Vec<Vertex> = vec![];
-        for p in path.iter() {
-            match p {
-                // Event::Begin { at: Endpoint } => {}
-                PathEvent::Line { from, .. } => vertices.push(Vertex {
-                    position: [from.x / 3.0, from.y / 3.0, 0.0],
-                    color: [1.0, 1.0, 0.0],
-                }),
-                PathEvent::End { last, first, .. } => {
-                    vertices.push(Vertex {
-                        position: [last.x / 3.0, last.y / 3.0, 0.0],
-                        color: [1.0, 1.0, 0.0],
-                    });
-                    vertices.push(Vertex {
-                        position: [first.x / 3.0, first.y / 3.0, 0.0],
-                        color: [1.0, 1.0, 0.0],
-                    })
-                }
-                _ => continue, // Event::Quadratic {
-                               //     from: Endpoint,
-                               //     ctrl: ControlPoint,
-                               //     to: Endpoint,
-                               // } => {}
-                               // Event::Cubic {
-                               //     from: Endpoint,
-                               //     ctrl1: ControlPoint,
-                               //     ctrl2: ControlPoint,
-                               //     to: Endpoint,
-                               // } => {}
-                               // Event::End {
-                               //     last: Endpoint,
-                               //     first: Endpoint,
-                               //     close: bool,
-                               // } => {}
-            }
-        }
-
-        // let vertices = points
-        //     .into_iter()
-        //     .map(|p| Vertex {
-        //         position: [p.x / 3.0, p.y / 3.0, 0.0],
-        //         color: [1.0, 1.0, 0.0],
-        //     })
-        //     .collect::<Vec<Vertex>>();
-
-        // // // Bézierish curve... Nope.
-        // let mut builder = Path::builder();
-        // builder.begin(point(0.0, 0.0));
-        // builder.cubic_bezier_to(point(0.5, -0.8), point(1.0, -1.3), point(2.0, 1.0));
-        // builder.line_to(point(2.0, 1.1));
-        // builder.cubic_bezier_to(point(1.0, -1.2), point(0.5, -0.7), point(0.0, 0.1));
-        // builder.close();
-        // let path = builder.build();
-
-        // for p in &path {
-        //     eprintln!("{:?}", p);
-        // }
-
-        // {
-        //     // fill_tess
-        //     //     .tessellate_path(
-        //     //         &path,
-        //     //         &FillOptions::tolerance(0.01),
-        //     //         &mut BuffersBuilder::new(&mut geometry, |vertex: FillVertex| Vertex {
-        //     //             position: [vertex.position().x / 5.0, vertex.position().y / 5.0, 0.0],
-        //     //             color: [1.0, 1.0, 0.0],
-        //     //         }),
-        //     //     )
-        //     //     .unwrap();
-        //     let mut stroke_tess = StrokeTessellator::new();
-        //     let _ = stroke_tess.tessellate(
-        //         &path,
-        //         &StrokeOptions::default(),
-        //         &mut BuffersBuilder::new(&mut geometry, |vertex: StrokeVertex| Vertex {
-        //             position: [vertex.position().x / 5.0, vertex.position().y / 5.0, 0.0],
-        //             color: [1.0, 1.0, 0.0],
-        //         }),
-        //     );
-        // }
-
-        // let vertices = geometry
-        //     .vertices
-        //     .into_iter()
-        //     .map(|p| Vertex {
-        //         position: [p.x / 3.0, p.y / 3.0, 0.0],
-        //         color: [1.0, 1.0, 0.0],
-        //     })
-        //     .collect::<Vec<Vertex>>();
-        // let vertices = geometry.vertices;
-        // eprintln!("VERTICES {vertices:#?}");
-
-        // let indices = geometry.indices;
-        // eprintln!("INDICES {indices:?}");
-
+    async fn new(
+        window: &Window,
+        scene: Scene,
+        options: RenderOptions,
+    ) -> Result<Self, UnsupportedSampleCount> {
         let instance = wgpu::Instance::new(wgpu::Backends::all());
         let surface = unsafe { instance.create_surface(window) };
         let adapter = instance
@@ -361,85 +301,33 @@ impl State {
         };
         surface.configure(&device, &config);
 
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/shader.wgsl").into()),
-        });
-
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[],
-                push_constant_ranges: &[],
-            });
-
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &&shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::desc()],
-            },
-            fragment: Some(wgpu::FragmentState {
-                // 3.
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    // 4.
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::LineStrip, // 1.
-                strip_index_format: None,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                ..Default::default() // front_face: wgpu::FrontFace::Ccw, // 2.
-                                     // cull_mode: Some(wgpu::Face::Back),
-                                     // // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
-                                     // polygon_mode: wgpu::PolygonMode::Fill,
-                                     // // Requires Features::DEPTH_CLIP_CONTROL
-                                     // unclipped_depth: false,
-                                     // // Requires Features::CONSERVATIVE_RASTERIZATION
-                                     // conservative: false,
-            },
-            depth_stencil: None, // 1.
-            multisample: wgpu::MultisampleState {
-                count: 1,                         // 2.
-                mask: !0,                         // 3.
-                alpha_to_coverage_enabled: false, // 4.
-            },
-            multiview: None, // 5.
-        });
-
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(&vertices),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
-        let num_vertices = vertices.len() as u32;
-
-        // let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        //     label: Some("Index Buffer"),
-        //     contents: bytemuck::cast_slice(&indices),
-        //     usage: wgpu::BufferUsages::INDEX,
-        // });
-        // let num_indices = indices.len() as u32;
-
-        Self {
+        let sample_count = supported_sample_count(&adapter, config.format, options.sample_count)?;
+        let mut renderer = Renderer::new(
+            &device,
+            &queue,
+            config.format,
+            sample_count,
+            config.width,
+            config.height,
+        );
+        renderer.set_feathering(options.feathering);
+
+        let scale_factor = window.scale_factor();
+        let logical_size = size.to_logical::<f32>(scale_factor);
+        let camera = Camera::new(logical_size.width, logical_size.height);
+
+        Ok(Self {
             surface,
             device,
             queue,
             config,
             size,
-            render_pipeline,
-            vertex_buffer,
-            num_vertices,
-            // index_buffer,
-            // num_indices,
-        }
+            renderer,
+            scene,
+            scale_factor,
+            camera,
+            camera_controller: CameraController::new(10.0),
+        })
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
@@ -448,15 +336,65 @@ impl State {
             self.config.width = new_size.width;
             self.config.height = new_size.height;
             self.surface.configure(&self.device, &self.config);
+            self.renderer
+                .resize(&self.device, new_size.width, new_size.height);
+            let logical_size = new_size.to_logical::<f32>(self.scale_factor);
+            self.camera.width = logical_size.width;
+            self.camera.height = logical_size.height;
         }
     }
 
-    #[allow(unused_variables)]
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
     fn input(&mut self, event: &WindowEvent) -> bool {
-        false
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                // F toggles edge feathering.
+                VirtualKeyCode::F => {
+                    let feathering = !self.renderer.feathering();
+                    info!("Edge feathering {}", if feathering { "on" } else { "off" });
+                    self.renderer.set_feathering(feathering);
+                    return true;
+                }
+                // S saves the scene as scene.svg, sized like the window.
+                #[cfg(not(target_arch = "wasm32"))]
+                VirtualKeyCode::S => {
+                    let size = lyon::math::size(self.camera.width, self.camera.height);
+                    let svg = svg::export(&self.scene, size);
+                    match std::fs::write("scene.svg", svg) {
+                        Ok(()) => info!("Saved the scene to scene.svg"),
+                        Err(e) => log::error!("Couldn't save the scene: {}", e),
+                    }
+                    return true;
+                }
+                _ => {}
+            }
+        }
+        self.camera_controller
+            .process_events(event, &mut self.camera, self.scale_factor)
     }
 
-    fn update(&mut self) {}
+    fn update(&mut self) {
+        self.camera_controller.update_camera(&mut self.camera);
+        self.renderer.prepare(
+            &self.device,
+            &self.queue,
+            &self.scene,
+            &self.camera,
+            self.scale_factor as f32,
+        );
+    }
 
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
         let output = self.surface.get_current_texture()?;
@@ -470,36 +408,8 @@ impl State {
                 label: Some("Render Encoder"),
             });
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.0,
-                            g: 0.0,
-                            b: 0.0,
-                            a: 0.0,
-                        }),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
-
-            render_pass.set_pipeline(&self.render_pipeline);
-            // render_pass.draw(0..3, 0..1);
-
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.draw(0..self.num_vertices, 0..1);
-
-            // render_pass.set_pipeline(&self.render_pipeline);
-            // render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            // render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16); // 1.
-            // render_pass.draw_indexed(0..self.num_indices, 0, 0..1); // 2.
-        }
+        self.renderer
+            .render(&mut encoder, &view, self.scene.background);
 
         self.queue.submit(iter::once(encoder.finish()));
         output.present();
@@ -510,6 +420,11 @@ impl State {
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 pub async fn run() {
+    run_with_scene(default_scene(), RenderOptions::default()).await
+}
+
+/// Opens a window and renders `scene` until the window is closed.
+pub async fn run_with_scene(scene: Scene, options: RenderOptions) {
     init_logger();
 
     cfg_if::cfg_if! {
@@ -534,36 +449,44 @@ pub async fn run() {
     }
 
     // State::new uses async code, so we're going to wait for it to finish
-    let mut state = State::new(&window).await;
+    let mut state = match State::new(&window, scene, options).await {
+        Ok(state) => state,
+        Err(e) => {
+            log::error!("Can't render the scene: {}", e);
+            return;
+        }
+    };
 
     event_loop.run(move |event, _, control_flow| {
         match event {
             Event::WindowEvent {
                 ref event,
                 window_id,
-            } if window_id == window.id() => {
-                if !state.input(event) {
-                    match event {
-                        WindowEvent::CloseRequested
-                        | WindowEvent::KeyboardInput {
-                            input:
-                                KeyboardInput {
-                                    state: ElementState::Pressed,
-                                    virtual_keycode: Some(VirtualKeyCode::Escape),
-                                    ..
-                                },
-                            ..
-                        } => *control_flow = ControlFlow::Exit,
-                        WindowEvent::Resized(physical_size) => {
-                            info!("SHOHEI: resizing...");
-                            state.resize(*physical_size);
-                        }
-                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                            // new_inner_size is &mut so w have to dereference it twice
-                            state.resize(**new_inner_size);
-                        }
-                        _ => {}
+            } if window_id == window.id() && !state.input(event) => {
+                match event {
+                    WindowEvent::CloseRequested
+                    | WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            },
+                        ..
+                    } => *control_flow = ControlFlow::Exit,
+                    WindowEvent::Resized(physical_size) => {
+                        info!("SHOHEI: resizing...");
+                        state.resize(*physical_size);
+                    }
+                    WindowEvent::ScaleFactorChanged {
+                        scale_factor,
+                        new_inner_size,
+                    } => {
+                        state.set_scale_factor(*scale_factor);
+                        // new_inner_size is &mut so w have to dereference it twice
+                        state.resize(**new_inner_size);
                     }
+                    _ => {}
                 }
             }
             Event::RedrawRequested(window_id) if window_id == window.id() => {
@@ -0,0 +1,400 @@
+//! What fills and strokes are painted with: a flat color, a gradient or an
+//! image.
+//!
+//! Gradient and image geometry is given in the path space of the shape it
+//! paints, before the shape's transform, and can be moved further with its
+//! own `transform`, like SVG's `gradientTransform` and `patternTransform`.
+
+use lyon::math::{Point, Transform};
+
+use crate::scene::{premultiply, ImageId, Scene};
+
+/// Stops past this many are dropped. They are stored inline in the paint's
+/// uniform, which has to fit in 256 bytes.
+pub const MAX_STOPS: usize = 8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Paint {
+    /// RGBA, not premultiplied.
+    Solid([f32; 4]),
+    LinearGradient(LinearGradient),
+    RadialGradient(RadialGradient),
+    Image(ImagePaint),
+}
+
+impl Paint {
+    /// The paint with its geometry mapped through `transform`.
+    pub fn transformed(&self, transform: &Transform) -> Paint {
+        match self {
+            Paint::Solid(color) => Paint::Solid(*color),
+            Paint::LinearGradient(gradient) => {
+                let mut gradient = gradient.clone();
+                gradient.transform = gradient.transform.then(transform);
+                Paint::LinearGradient(gradient)
+            }
+            Paint::RadialGradient(gradient) => {
+                let mut gradient = gradient.clone();
+                gradient.transform = gradient.transform.then(transform);
+                Paint::RadialGradient(gradient)
+            }
+            Paint::Image(image) => {
+                let mut image = image.clone();
+                image.transform = image.transform.then(transform);
+                Paint::Image(image)
+            }
+        }
+    }
+
+    /// The paint with its colors multiplied by `tint`, RGBA not
+    /// premultiplied, the way an [`Instance`](crate::scene::Instance) tints
+    /// it. Images are left as they are.
+    pub fn tinted(&self, tint: [f32; 4]) -> Paint {
+        let tint_color = |color: &mut [f32; 4]| {
+            for (c, t) in color.iter_mut().zip(tint) {
+                *c *= t;
+            }
+        };
+        let mut paint = self.clone();
+        match &mut paint {
+            Paint::Solid(color) => tint_color(color),
+            Paint::LinearGradient(gradient) => gradient
+                .stops
+                .iter_mut()
+                .for_each(|s| tint_color(&mut s.color)),
+            Paint::RadialGradient(gradient) => gradient
+                .stops
+                .iter_mut()
+                .for_each(|s| tint_color(&mut s.color)),
+            Paint::Image(_) => {}
+        }
+        paint
+    }
+
+    /// The image the paint samples, if any, and whether it repeats.
+    pub(crate) fn texture(&self) -> Option<(ImageId, bool)> {
+        match self {
+            Paint::Image(image) => Some((image.image, image.repeat)),
+            _ => None,
+        }
+    }
+
+    /// The uniform the shader paints with. Gradients include `opacity`; solid
+    /// colors and images are faded through the vertex color instead. Solid
+    /// colors all get [`PaintUniform::solid`].
+    pub(crate) fn uniform(&self, opacity: f32, scene: &Scene) -> PaintUniform {
+        match self {
+            Paint::Solid(_) => PaintUniform::solid(),
+            Paint::LinearGradient(gradient) => {
+                let mut uniform = PaintUniform::gradient(
+                    KIND_LINEAR,
+                    gradient.spread,
+                    &gradient.transform,
+                    &gradient.stops,
+                    opacity,
+                );
+                uniform.points = [
+                    gradient.start.x,
+                    gradient.start.y,
+                    gradient.end.x,
+                    gradient.end.y,
+                ];
+                uniform
+            }
+            Paint::RadialGradient(gradient) => {
+                let mut uniform = PaintUniform::gradient(
+                    KIND_RADIAL,
+                    gradient.spread,
+                    &gradient.transform,
+                    &gradient.stops,
+                    opacity,
+                );
+                let radius = gradient.radius.max(0.0);
+                // The math in the shader needs the focal point strictly
+                // inside the circle.
+                let mut to_focal = gradient.focal - gradient.center;
+                let limit = radius * 0.999;
+                if to_focal.length() > limit {
+                    to_focal = to_focal.normalize() * limit;
+                }
+                let focal = gradient.center + to_focal;
+                uniform.points = [gradient.center.x, gradient.center.y, focal.x, focal.y];
+                uniform.radius = radius;
+                uniform
+            }
+            Paint::Image(image) => match scene.image(image.image) {
+                Some(pixels) => PaintUniform::image(image, pixels.width, pixels.height),
+                None => {
+                    log::warn!(
+                        "Image paint uses an image not in the scene: {:?}",
+                        image.image
+                    );
+                    // A gradient without stops, which paints nothing.
+                    PaintUniform::gradient(KIND_LINEAR, Spread::Pad, &image.transform, &[], 1.0)
+                }
+            },
+        }
+    }
+}
+
+impl From<[f32; 4]> for Paint {
+    fn from(color: [f32; 4]) -> Self {
+        Paint::Solid(color)
+    }
+}
+
+impl From<LinearGradient> for Paint {
+    fn from(gradient: LinearGradient) -> Self {
+        Paint::LinearGradient(gradient)
+    }
+}
+
+impl From<RadialGradient> for Paint {
+    fn from(gradient: RadialGradient) -> Self {
+        Paint::RadialGradient(gradient)
+    }
+}
+
+impl From<ImagePaint> for Paint {
+    fn from(image: ImagePaint) -> Self {
+        Paint::Image(image)
+    }
+}
+
+/// What a gradient does past its first and last stops.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Spread {
+    /// Keep the color of the nearest end.
+    #[default]
+    Pad,
+    /// Start over from the first stop.
+    Repeat,
+    /// Go back and forth.
+    Reflect,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GradientStop {
+    /// Position along the gradient, from 0 to 1.
+    pub offset: f32,
+    /// RGBA, not premultiplied.
+    pub color: [f32; 4],
+}
+
+/// Varies along the line from `start` to `end`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinearGradient {
+    pub start: Point,
+    pub end: Point,
+    pub stops: Vec<GradientStop>,
+    pub spread: Spread,
+    pub transform: Transform,
+}
+
+impl LinearGradient {
+    pub fn new(start: Point, end: Point) -> Self {
+        Self {
+            start,
+            end,
+            stops: Vec::new(),
+            spread: Spread::Pad,
+            transform: Transform::identity(),
+        }
+    }
+
+    pub fn add_stop(&mut self, offset: f32, color: [f32; 4]) {
+        self.stops.push(GradientStop { offset, color });
+    }
+
+    pub fn with_stop(mut self, offset: f32, color: [f32; 4]) -> Self {
+        self.add_stop(offset, color);
+        self
+    }
+
+    pub fn with_spread(mut self, spread: Spread) -> Self {
+        self.spread = spread;
+        self
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+}
+
+/// Varies from `focal`, at offset 0, to the circle around `center`, at
+/// offset 1. The focal point is moved inside the circle if it isn't.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RadialGradient {
+    pub center: Point,
+    pub radius: f32,
+    pub focal: Point,
+    pub stops: Vec<GradientStop>,
+    pub spread: Spread,
+    pub transform: Transform,
+}
+
+impl RadialGradient {
+    /// A gradient with its focal point at the center.
+    pub fn new(center: Point, radius: f32) -> Self {
+        Self {
+            center,
+            radius,
+            focal: center,
+            stops: Vec::new(),
+            spread: Spread::Pad,
+            transform: Transform::identity(),
+        }
+    }
+
+    pub fn add_stop(&mut self, offset: f32, color: [f32; 4]) {
+        self.stops.push(GradientStop { offset, color });
+    }
+
+    pub fn with_stop(mut self, offset: f32, color: [f32; 4]) -> Self {
+        self.add_stop(offset, color);
+        self
+    }
+
+    pub fn with_focal(mut self, focal: Point) -> Self {
+        self.focal = focal;
+        self
+    }
+
+    pub fn with_spread(mut self, spread: Spread) -> Self {
+        self.spread = spread;
+        self
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+}
+
+/// Paints with an image added to the scene, e.g. a logo, or tiles it to
+/// fill the whole shape, e.g. with a hatching pattern.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImagePaint {
+    pub image: ImageId,
+    /// Maps image pixels to path space. With the identity the image's top
+    /// left corner is at the origin and one pixel is one unit.
+    pub transform: Transform,
+    /// Tile the image in both directions instead of leaving the shape
+    /// transparent outside of it.
+    pub repeat: bool,
+}
+
+impl ImagePaint {
+    pub fn new(image: ImageId) -> Self {
+        Self {
+            image,
+            transform: Transform::identity(),
+            repeat: false,
+        }
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: bool) -> Self {
+        self.repeat = repeat;
+        self
+    }
+}
+
+// Must match `Paint` in shaders/shader.wgsl.
+const KIND_SOLID: u32 = 0;
+const KIND_LINEAR: u32 = 1;
+const KIND_RADIAL: u32 = 2;
+const KIND_IMAGE: u32 = 3;
+
+/// A paint as the fragment shader reads it.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct PaintUniform {
+    kind: u32,
+    // For images, 1 to repeat.
+    spread: u32,
+    stop_count: u32,
+    _padding: u32,
+    // Rows of the 2x3 matrix from path space to gradient space, or to texture
+    // coordinates for images.
+    transform: [[f32; 4]; 2],
+    // Linear: start and end. Radial: center and focal point.
+    points: [f32; 4],
+    radius: f32,
+    _padding2: [f32; 3],
+    offsets: [[f32; 4]; MAX_STOPS / 4],
+    // Premultiplied RGBA.
+    colors: [[f32; 4]; MAX_STOPS],
+}
+
+impl PaintUniform {
+    pub fn solid() -> Self {
+        Self {
+            kind: KIND_SOLID,
+            ..bytemuck::Zeroable::zeroed()
+        }
+    }
+
+    fn image(image: &ImagePaint, width: u32, height: u32) -> Self {
+        let mut uniform = Self::solid();
+        uniform.kind = KIND_IMAGE;
+        uniform.spread = image.repeat as u32;
+        let inverse = image.transform.inverse().unwrap_or_else(|| {
+            log::warn!("Image transform can't be inverted: {:?}", image.transform);
+            Transform::identity()
+        });
+        let to_uv = inverse.then_scale(1.0 / width.max(1) as f32, 1.0 / height.max(1) as f32);
+        uniform.set_transform(&to_uv);
+        uniform
+    }
+
+    fn gradient(
+        kind: u32,
+        spread: Spread,
+        transform: &Transform,
+        stops: &[GradientStop],
+        opacity: f32,
+    ) -> Self {
+        let mut uniform = Self::solid();
+        uniform.kind = kind;
+        uniform.spread = match spread {
+            Spread::Pad => 0,
+            Spread::Repeat => 1,
+            Spread::Reflect => 2,
+        };
+        let inverse = transform.inverse().unwrap_or_else(|| {
+            log::warn!("Gradient transform can't be inverted: {:?}", transform);
+            Transform::identity()
+        });
+        uniform.set_transform(&inverse);
+        if stops.len() > MAX_STOPS {
+            log::warn!(
+                "Gradient has {} stops, only the first {} are used",
+                stops.len(),
+                MAX_STOPS
+            );
+        }
+        // Like SVG, offsets are clamped to 0..1 and to at least the previous
+        // stop's.
+        let mut previous = 0.0f32;
+        for (i, stop) in stops.iter().take(MAX_STOPS).enumerate() {
+            let offset = stop.offset.clamp(previous, 1.0);
+            uniform.offsets[i / 4][i % 4] = offset;
+            uniform.colors[i] = premultiply(stop.color, opacity);
+            previous = offset;
+            uniform.stop_count += 1;
+        }
+        uniform
+    }
+
+    fn set_transform(&mut self, transform: &Transform) {
+        self.transform = [
+            [transform.m11, transform.m21, transform.m31, 0.0],
+            [transform.m12, transform.m22, transform.m32, 0.0],
+        ];
+    }
+}
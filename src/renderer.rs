@@ -0,0 +1,720 @@
+//! GPU resources and draw code shared by the window and headless renderers.
+//! Nothing in here knows where the frame ends up.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::num::NonZeroU32;
+
+use wgpu::util::DeviceExt;
+
+use crate::buffers::{GeometryBuffers, SlotBuffer};
+use crate::camera::Camera;
+use crate::image::Image;
+use crate::paint::PaintUniform;
+use crate::scene::{premultiply, ImageId, Scene};
+use crate::tessellate::{zoom_level, Draw, DrawKind, TessellationCache, Tessellator, FEATHER};
+use crate::{InstanceRaw, Vertex};
+
+// Only the stencil part is used, for clipping. WebGL2 has no stencil-only
+// format.
+const STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24PlusStencil8;
+
+/// Settings shared by the window and headless renderers.
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    /// MSAA samples per pixel, 1 or 4. 8 is impossible with wgpu 0.13,
+    /// whose render passes take no other count even on adapters that could
+    /// do more, so creating a renderer with it or any other count fails
+    /// with [`UnsupportedSampleCount`]. 4 is lowered to 1 on adapters that
+    /// can't multisample the output format.
+    pub sample_count: u32,
+    /// Smooth edges by fading them out over a device pixel instead, or on
+    /// top of MSAA. Meant for WebGL2, where multisampling may be missing or
+    /// slow. The F key toggles it in the window.
+    pub feathering: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        if cfg!(target_arch = "wasm32") {
+            Self {
+                sample_count: 1,
+                feathering: true,
+            }
+        } else {
+            Self {
+                sample_count: 4,
+                feathering: false,
+            }
+        }
+    }
+}
+
+/// A [`RenderOptions::sample_count`] other than 1 or 4.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UnsupportedSampleCount(pub u32);
+
+impl fmt::Display for UnsupportedSampleCount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} samples per pixel are not supported, only 1 or 4 are",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedSampleCount {}
+
+/// The sample count to render to `format` on `adapter` with: `requested` if
+/// it is 1, or 4 and the adapter can multisample `format`, otherwise 1.
+/// Other counts are an error, see [`RenderOptions::sample_count`].
+pub fn supported_sample_count(
+    adapter: &wgpu::Adapter,
+    format: wgpu::TextureFormat,
+    requested: u32,
+) -> Result<u32, UnsupportedSampleCount> {
+    match requested {
+        1 => Ok(1),
+        4 => {
+            let flags = adapter.get_texture_format_features(format).flags;
+            if flags.contains(
+                wgpu::TextureFormatFeatureFlags::MULTISAMPLE
+                    | wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE,
+            ) {
+                Ok(4)
+            } else {
+                log::warn!(
+                    "The adapter can't multisample {:?}, rendering with 1 sample per pixel",
+                    format
+                );
+                Ok(1)
+            }
+        }
+        _ => Err(UnsupportedSampleCount(requested)),
+    }
+}
+
+pub struct Renderer {
+    sample_count: u32,
+    format: wgpu::TextureFormat,
+    // Multisampled color target, resolved into the output view. Only there
+    // when sample_count > 1.
+    msaa_view: Option<wgpu::TextureView>,
+    // Counts the clips each pixel is inside of, see DrawKind.
+    stencil_view: wgpu::TextureView,
+    feathering: bool,
+    render_pipeline: wgpu::RenderPipeline,
+    push_clip_pipeline: wgpu::RenderPipeline,
+    pop_clip_pipeline: wgpu::RenderPipeline,
+    geometry: GeometryBuffers,
+    instance_buffer: SlotBuffer,
+    draws: Vec<Draw>,
+    // One uniform per paint, each in its own slot selected with a dynamic
+    // offset.
+    paint_bind_group_layout: wgpu::BindGroupLayout,
+    paint_stride: wgpu::BufferAddress,
+    paint_buffer: SlotBuffer,
+    paint_bind_group: wgpu::BindGroup,
+    // Two bind groups per scene image, clamped and repeating, made the first
+    // time it is seen and dropped once it's no longer in the scene. Draws
+    // without an image, or whose image couldn't be uploaded, get the
+    // transparent one.
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    clamp_sampler: wgpu::Sampler,
+    repeat_sampler: wgpu::Sampler,
+    textures: HashMap<ImageId, [wgpu::BindGroup; 2]>,
+    transparent_texture: wgpu::BindGroup,
+    // Scene version, scale factor and zoom level the buffers were built for,
+    // None also when they need rebuilding for another reason.
+    scene_version: Option<u64>,
+    scale_factor: f32,
+    zoom_level: i32,
+    tessellator: Tessellator,
+    tessellations: TessellationCache,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+}
+
+impl Renderer {
+    /// Creates a renderer drawing into `width` by `height` textures of the
+    /// given `format`. `sample_count` has to be one the adapter supports, see
+    /// [`supported_sample_count`].
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/shader.wgsl").into()),
+        });
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::cast_slice(&[Camera::new(1.0, 1.0).uniform(1.0, 0.0)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let camera_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("camera_bind_group_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera_bind_group"),
+            layout: &camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        let paint_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("paint_bind_group_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<PaintUniform>() as wgpu::BufferAddress,
+                        ),
+                    },
+                    count: None,
+                }],
+            });
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let paint_stride = (std::mem::size_of::<PaintUniform>() as wgpu::BufferAddress)
+            .div_ceil(alignment)
+            * alignment;
+
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("texture_bind_group_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+        let clamp_sampler = create_sampler(device, wgpu::AddressMode::ClampToEdge);
+        let repeat_sampler = create_sampler(device, wgpu::AddressMode::Repeat);
+        let [transparent_texture, _] = create_texture_bind_groups(
+            device,
+            queue,
+            &texture_bind_group_layout,
+            [&clamp_sampler, &repeat_sampler],
+            &Image {
+                width: 1,
+                height: 1,
+                pixels: vec![0; 4],
+            },
+        );
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[
+                    &camera_bind_group_layout,
+                    &paint_bind_group_layout,
+                    &texture_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let create_pipeline = |label, write_mask, pass_op| {
+            create_render_pipeline(
+                device,
+                &render_pipeline_layout,
+                &shader,
+                label,
+                format,
+                sample_count,
+                write_mask,
+                pass_op,
+            )
+        };
+        let render_pipeline = create_pipeline(
+            "Render Pipeline",
+            wgpu::ColorWrites::ALL,
+            wgpu::StencilOperation::Keep,
+        );
+        let push_clip_pipeline = create_pipeline(
+            "Push Clip Pipeline",
+            wgpu::ColorWrites::empty(),
+            wgpu::StencilOperation::IncrementClamp,
+        );
+        let pop_clip_pipeline = create_pipeline(
+            "Pop Clip Pipeline",
+            wgpu::ColorWrites::empty(),
+            wgpu::StencilOperation::DecrementClamp,
+        );
+
+        let instance_buffer = SlotBuffer::new(
+            device,
+            "Instance Buffer",
+            wgpu::BufferUsages::VERTEX,
+            std::mem::size_of::<InstanceRaw>(),
+        );
+        let paint_buffer = SlotBuffer::new(
+            device,
+            "Paint Buffer",
+            wgpu::BufferUsages::UNIFORM,
+            paint_stride as usize,
+        );
+        let paint_bind_group =
+            create_paint_bind_group(device, &paint_bind_group_layout, paint_buffer.buffer());
+
+        Self {
+            sample_count,
+            format,
+            msaa_view: create_msaa_view(device, format, sample_count, width, height),
+            stencil_view: create_stencil_view(device, sample_count, width, height),
+            feathering: false,
+            render_pipeline,
+            push_clip_pipeline,
+            pop_clip_pipeline,
+            geometry: GeometryBuffers::new(device),
+            instance_buffer,
+            draws: Vec::new(),
+            paint_bind_group_layout,
+            paint_stride,
+            paint_buffer,
+            paint_bind_group,
+            texture_bind_group_layout,
+            clamp_sampler,
+            repeat_sampler,
+            textures: HashMap::new(),
+            transparent_texture,
+            scene_version: None,
+            scale_factor: 0.0,
+            zoom_level: 0,
+            tessellator: Tessellator::new(),
+            tessellations: TessellationCache::new(),
+            camera_buffer,
+            camera_bind_group,
+        }
+    }
+
+    /// Recreates the multisampled target and the stencil buffer for a new
+    /// output size.
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.msaa_view = create_msaa_view(device, self.format, self.sample_count, width, height);
+        self.stencil_view = create_stencil_view(device, self.sample_count, width, height);
+    }
+
+    pub fn feathering(&self) -> bool {
+        self.feathering
+    }
+
+    /// Turns edge feathering on or off, see [`RenderOptions::feathering`].
+    /// Takes effect at the next [`Renderer::prepare`].
+    pub fn set_feathering(&mut self, feathering: bool) {
+        if feathering != self.feathering {
+            self.feathering = feathering;
+            self.scene_version = None;
+        }
+    }
+
+    /// Uploads the camera and, if the scene, the scale factor or the zoom
+    /// level changed since the last call, the scene geometry and paints,
+    /// along with any image it hasn't seen yet. Reuses an earlier
+    /// tessellation when there is one, and otherwise the geometry of the
+    /// shapes that didn't change. Only what changed is written again, see
+    /// [`GeometryBuffers`] and [`SlotBuffer`].
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scene: &Scene,
+        camera: &Camera,
+        scale_factor: f32,
+    ) {
+        let feather = if self.feathering { FEATHER } else { 0.0 };
+        queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&[camera.uniform(scale_factor, feather)]),
+        );
+
+        let zoom_level = zoom_level(camera.zoom);
+        if self.scene_version == Some(scene.version())
+            && self.scale_factor == scale_factor
+            && self.zoom_level == zoom_level
+        {
+            return;
+        }
+
+        self.textures.retain(|id, _| scene.image(*id).is_some());
+        for (id, image) in scene.images() {
+            if self.textures.contains_key(&id) {
+                continue;
+            }
+            let max = device.limits().max_texture_dimension_2d;
+            if image.width == 0 || image.height == 0 || image.width > max || image.height > max {
+                log::warn!(
+                    "Can't upload a {}x{} image, the limit is {}x{}",
+                    image.width,
+                    image.height,
+                    max,
+                    max
+                );
+                continue;
+            }
+            if image.pixels.len() != (image.width * image.height * 4) as usize {
+                log::warn!(
+                    "Image has {} bytes of pixels instead of {} for {}x{}",
+                    image.pixels.len(),
+                    image.width * image.height * 4,
+                    image.width,
+                    image.height
+                );
+                continue;
+            }
+            let bind_groups = create_texture_bind_groups(
+                device,
+                queue,
+                &self.texture_bind_group_layout,
+                [&self.clamp_sampler, &self.repeat_sampler],
+                image,
+            );
+            self.textures.insert(id, bind_groups);
+        }
+
+        let tessellation = self.tessellations.get(
+            scene,
+            &mut self.tessellator,
+            scale_factor,
+            zoom_level,
+            self.feathering,
+        );
+        self.geometry.upload(device, queue, tessellation);
+        let instances = tessellation.instances.iter().map(bytemuck::bytes_of);
+        self.instance_buffer.upload(device, queue, instances);
+        let paints = tessellation.paints.iter().map(bytemuck::bytes_of);
+        if self.paint_buffer.upload(device, queue, paints) {
+            self.paint_bind_group = create_paint_bind_group(
+                device,
+                &self.paint_bind_group_layout,
+                self.paint_buffer.buffer(),
+            );
+        }
+        self.draws = tessellation.draws.clone();
+        self.scene_version = Some(scene.version());
+        self.scale_factor = scale_factor;
+        self.zoom_level = zoom_level;
+    }
+
+    /// Clears `view` to `background`, RGBA not premultiplied, and draws the
+    /// geometry uploaded by the last call to [`Renderer::prepare`].
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        background: [f32; 4],
+    ) {
+        let background = premultiply(background, 1.0);
+        // With MSAA, draw into the multisampled texture and resolve into
+        // `view` at the end of the pass.
+        let (view, resolve_target) = match &self.msaa_view {
+            Some(msaa_view) => (msaa_view, Some(view)),
+            None => (view, None),
+        };
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: background[0] as f64,
+                        g: background[1] as f64,
+                        b: background[2] as f64,
+                        a: background[3] as f64,
+                    }),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.stencil_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: false,
+                }),
+                stencil_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0),
+                    store: false,
+                }),
+            }),
+        });
+
+        if !self.draws.is_empty() {
+            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.geometry.vertex_buffer().slice(..));
+            render_pass.set_vertex_buffer(1, self.instance_buffer.buffer().slice(..));
+            render_pass.set_index_buffer(
+                self.geometry.index_buffer().slice(..),
+                self.geometry.index_format(),
+            );
+            // Only what changes from one draw to the next is set again.
+            let mut previous: Option<&Draw> = None;
+            for draw in &self.draws {
+                if previous.map(|p| p.kind) != Some(draw.kind) {
+                    render_pass.set_pipeline(match draw.kind {
+                        DrawKind::Paint => &self.render_pipeline,
+                        DrawKind::PushClip => &self.push_clip_pipeline,
+                        DrawKind::PopClip => &self.pop_clip_pipeline,
+                    });
+                }
+                if previous.map(|p| p.stencil) != Some(draw.stencil) {
+                    render_pass.set_stencil_reference(draw.stencil);
+                }
+                if previous.map(|p| p.paint) != Some(draw.paint) {
+                    let offset = draw.paint as wgpu::BufferAddress * self.paint_stride;
+                    render_pass.set_bind_group(1, &self.paint_bind_group, &[offset as u32]);
+                }
+                if previous.map(|p| p.image) != Some(draw.image) {
+                    let texture = draw
+                        .image
+                        .and_then(|(id, repeat)| Some(&self.textures.get(&id)?[repeat as usize]))
+                        .unwrap_or(&self.transparent_texture);
+                    render_pass.set_bind_group(2, texture, &[]);
+                }
+                previous = Some(draw);
+                render_pass.draw_indexed(draw.indices.clone(), 0, draw.instances.clone());
+            }
+        }
+    }
+}
+
+fn create_msaa_view(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    width: u32,
+    height: u32,
+) -> Option<wgpu::TextureView> {
+    if sample_count <= 1 {
+        return None;
+    }
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Multisampled Texture"),
+        size: wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+    });
+    Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+}
+
+fn create_stencil_view(
+    device: &wgpu::Device,
+    sample_count: u32,
+    width: u32,
+    height: u32,
+) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Stencil Texture"),
+        size: wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: STENCIL_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+    });
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+// Every pipeline draws the same vertices with the same shader, only passing
+// pixels whose stencil value is the reference. `pass_op` is what happens to
+// the stencil value of the pixels drawn.
+#[allow(clippy::too_many_arguments)]
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    label: &str,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    write_mask: wgpu::ColorWrites,
+    pass_op: wgpu::StencilOperation,
+) -> wgpu::RenderPipeline {
+    let stencil = wgpu::StencilFaceState {
+        compare: wgpu::CompareFunction::Equal,
+        fail_op: wgpu::StencilOperation::Keep,
+        depth_fail_op: wgpu::StencilOperation::Keep,
+        pass_op,
+    };
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[Vertex::desc(), InstanceRaw::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                write_mask,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            ..Default::default()
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: STENCIL_FORMAT,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Always,
+            stencil: wgpu::StencilState {
+                front: stencil,
+                back: stencil,
+                read_mask: 0xff,
+                write_mask: 0xff,
+            },
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
+// Binds one paint of `buffer`, picked with a dynamic offset.
+fn create_paint_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("paint_bind_group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                buffer,
+                offset: 0,
+                size: wgpu::BufferSize::new(
+                    std::mem::size_of::<PaintUniform>() as wgpu::BufferAddress
+                ),
+            }),
+        }],
+    })
+}
+
+fn create_sampler(device: &wgpu::Device, address_mode: wgpu::AddressMode) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("Image Sampler"),
+        address_mode_u: address_mode,
+        address_mode_v: address_mode,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    })
+}
+
+// Uploads `image`, which has to fit the device limits, as an sRGB texture,
+// and binds it with each sampler. There are no mipmaps, so images drawn much
+// smaller than their size alias.
+fn create_texture_bind_groups(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layout: &wgpu::BindGroupLayout,
+    samplers: [&wgpu::Sampler; 2],
+    image: &Image,
+) -> [wgpu::BindGroup; 2] {
+    let size = wgpu::Extent3d {
+        width: image.width,
+        height: image.height,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Image Texture"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+    });
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        &image.pixels,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: NonZeroU32::new(image.width * 4),
+            rows_per_image: NonZeroU32::new(image.height),
+        },
+        size,
+    );
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    samplers.map(|sampler| {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("texture_bind_group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        })
+    })
+}
@@ -0,0 +1,725 @@
+//! A retained list of shapes that `State` re-tessellates whenever it changes.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use lyon::math::Transform;
+use lyon::path::Path;
+use lyon::tessellation::{FillOptions, FillRule, StrokeOptions};
+
+use crate::dash::{dash, DashPattern};
+use crate::image::Image;
+use crate::paint::Paint;
+use crate::shapes::Primitive;
+use crate::tessellate::{
+    scale_factor, tolerance_for_scale, Geometry, Piece, PieceKey, Source, Tessellation,
+    Tessellator, MAX_CLIP_DEPTH,
+};
+use crate::InstanceRaw;
+
+/// The tolerance in `options` is ignored: the renderer picks one from the
+/// current pixel scale so curves stay smooth at any size. The fill rule in
+/// them decides which parts of self-intersecting or nested sub-paths are
+/// inside.
+#[derive(Clone, Debug)]
+pub struct FillStyle {
+    pub paint: Paint,
+    pub options: FillOptions,
+}
+
+/// As with [`FillStyle`], the tolerance in `options` is chosen by the
+/// renderer. Without a dash pattern the stroke is solid.
+#[derive(Clone, Debug)]
+pub struct StrokeStyle {
+    pub paint: Paint,
+    pub options: StrokeOptions,
+    pub dash: Option<DashPattern>,
+}
+
+/// A path plus how to paint it. Shapes with a higher `z_index` are drawn on
+/// top; shapes with the same `z_index` are drawn in insertion order.
+#[derive(Clone, Debug)]
+pub struct Shape {
+    pub path: Path,
+    /// The outline in a form lyon tessellates faster, which is then used
+    /// instead of `path` for the fill and the stroke. It has to describe the
+    /// same outline: export and feathering keep using `path`.
+    pub primitive: Option<Primitive>,
+    pub fill: Option<FillStyle>,
+    pub stroke: Option<StrokeStyle>,
+    pub transform: Transform,
+    pub z_index: i32,
+    /// Multiplies the alpha of both the fill and the stroke. They are faded
+    /// separately, so the fill shows through the inner half of a translucent
+    /// stroke.
+    pub opacity: f32,
+    pub group: Option<GroupId>,
+    /// Copies of the shape to draw instead of the shape itself. They share
+    /// its tessellation, so thousands of them cost little more than one.
+    pub instances: Vec<Instance>,
+}
+
+impl Shape {
+    pub fn new(path: Path) -> Self {
+        Self {
+            path,
+            primitive: None,
+            fill: None,
+            stroke: None,
+            transform: Transform::identity(),
+            z_index: 0,
+            opacity: 1.0,
+            group: None,
+            instances: Vec::new(),
+        }
+    }
+
+    /// A shape drawn from `primitive`, with the matching path.
+    pub fn from_primitive(primitive: Primitive) -> Self {
+        Self {
+            primitive: Some(primitive.clone()),
+            ..Self::new(primitive.to_path())
+        }
+    }
+
+    /// Fills the shape with `paint`, an RGBA color, a gradient or an image,
+    /// using the non-zero rule like SVG and the canvas API do.
+    pub fn with_fill(mut self, paint: impl Into<Paint>) -> Self {
+        self.fill = Some(FillStyle {
+            paint: paint.into(),
+            options: FillOptions::non_zero(),
+        });
+        self
+    }
+
+    /// Changes the fill rule of the fill set with [`Shape::with_fill`].
+    pub fn with_fill_rule(mut self, rule: FillRule) -> Self {
+        match &mut self.fill {
+            Some(fill) => fill.options.fill_rule = rule,
+            None => log::warn!("Fill rule set on a shape without a fill"),
+        }
+        self
+    }
+
+    pub fn with_stroke(mut self, paint: impl Into<Paint>, options: StrokeOptions) -> Self {
+        self.stroke = Some(StrokeStyle {
+            paint: paint.into(),
+            options,
+            dash: None,
+        });
+        self
+    }
+
+    /// Dashes the stroke set with [`Shape::with_stroke`].
+    pub fn with_dash(mut self, dash: DashPattern) -> Self {
+        match &mut self.stroke {
+            Some(stroke) => stroke.dash = Some(dash),
+            None => log::warn!("Dash pattern set on a shape without a stroke"),
+        }
+        self
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn with_z_index(mut self, z_index: i32) -> Self {
+        self.z_index = z_index;
+        self
+    }
+
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+
+    pub fn with_group(mut self, group: GroupId) -> Self {
+        self.group = Some(group);
+        self
+    }
+
+    pub fn with_instances(mut self, instances: Vec<Instance>) -> Self {
+        self.instances = instances;
+        self
+    }
+}
+
+/// One copy of an instanced shape, placed and tinted on the GPU.
+///
+/// All the copies are filled before any of them is stroked, so where they
+/// overlap, strokes show on top of the fills of copies drawn after them.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Instance {
+    /// Applied after the shape's own transform.
+    pub transform: Transform,
+    /// RGBA, not premultiplied, multiplying the shape's paints.
+    pub tint: [f32; 4],
+}
+
+impl Instance {
+    pub fn new(transform: Transform) -> Self {
+        Self {
+            transform,
+            tint: [1.0; 4],
+        }
+    }
+
+    pub fn with_tint(mut self, tint: [f32; 4]) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    fn raw(&self) -> InstanceRaw {
+        let t = &self.transform;
+        InstanceRaw {
+            transform: [[t.m11, t.m12], [t.m21, t.m22], [t.m31, t.m32]],
+            tint: premultiply(self.tint, 1.0),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ShapeId(u32);
+
+/// Settings shared by the shapes in it and in its descendant groups.
+///
+/// With a clip, only the parts of those shapes inside it are drawn. Clips of
+/// nested groups add up, each one cutting what its parent's left.
+///
+/// The opacity is multiplied into every shape's, which is cheap but not quite
+/// what SVG and CSS do: they draw the group on its own first and fade the
+/// result, so overlapping shapes of a translucent group don't show through
+/// each other.
+#[derive(Clone, Debug)]
+pub struct Group {
+    pub parent: Option<GroupId>,
+    pub opacity: f32,
+    pub clip: Option<Clip>,
+}
+
+impl Group {
+    pub fn new() -> Self {
+        Self {
+            parent: None,
+            opacity: 1.0,
+            clip: None,
+        }
+    }
+
+    pub fn with_parent(mut self, parent: GroupId) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+
+    pub fn with_clip(mut self, clip: Clip) -> Self {
+        self.clip = Some(clip);
+        self
+    }
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GroupId(u32);
+
+/// The area a group is drawn in: the inside of `path`, as filled with
+/// `fill_rule`, together with the inside of every path in `union`. Its
+/// edges are hard, or as smooth as MSAA makes them; feathering doesn't apply
+/// to them.
+#[derive(Clone, Debug)]
+pub struct Clip {
+    pub path: Path,
+    pub transform: Transform,
+    pub fill_rule: FillRule,
+    /// More areas the group is drawn in, each filled with its own rule, as
+    /// the children of an SVG `<clipPath>` are. Overlaps don't cancel out
+    /// whatever their winding.
+    pub union: Vec<(Path, FillRule)>,
+}
+
+impl Clip {
+    /// Clips to `path`, in scene space, using the non-zero rule.
+    pub fn new(path: Path) -> Self {
+        Self {
+            path,
+            transform: Transform::identity(),
+            fill_rule: FillRule::NonZero,
+            union: Vec::new(),
+        }
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn with_fill_rule(mut self, fill_rule: FillRule) -> Self {
+        self.fill_rule = fill_rule;
+        self
+    }
+
+    /// Also lets through the inside of `path`, filled with `fill_rule`.
+    pub fn with_union(mut self, path: Path, fill_rule: FillRule) -> Self {
+        self.union.push((path, fill_rule));
+        self
+    }
+
+    /// Every path with its fill rule, `path` first.
+    pub fn paths(&self) -> impl Iterator<Item = (&Path, FillRule)> {
+        std::iter::once((&self.path, self.fill_rule))
+            .chain(self.union.iter().map(|(path, rule)| (path, *rule)))
+    }
+}
+
+/// Unique across scenes, so a renderer drawing several of them can tell their
+/// images apart.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ImageId(u32);
+
+static NEXT_IMAGE_ID: AtomicU32 = AtomicU32::new(0);
+
+// Shapes, groups and scenes get a new revision whenever they may have
+// changed. Like image ids, revisions are unique across scenes.
+static NEXT_REVISION: AtomicU64 = AtomicU64::new(0);
+
+fn next_revision() -> u64 {
+    NEXT_REVISION.fetch_add(1, Ordering::Relaxed)
+}
+
+pub struct Scene {
+    /// Clear color, as RGBA, not premultiplied.
+    pub background: [f32; 4],
+    // With their revision, so the geometry of shapes that didn't change can
+    // be reused.
+    shapes: Vec<(ShapeId, u64, Shape)>,
+    // Indexed by GroupId. Groups live as long as the scene.
+    groups: Vec<Group>,
+    group_revisions: Vec<u64>,
+    images: Vec<(ImageId, Image)>,
+    next_id: u32,
+    version: u64,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self {
+            background: [0.0; 4],
+            shapes: Vec::new(),
+            groups: Vec::new(),
+            group_revisions: Vec::new(),
+            images: Vec::new(),
+            next_id: 0,
+            version: next_revision(),
+        }
+    }
+
+    pub fn add(&mut self, shape: Shape) -> ShapeId {
+        let id = ShapeId(self.next_id);
+        self.next_id += 1;
+        self.shapes.push((id, next_revision(), shape));
+        self.version = next_revision();
+        id
+    }
+
+    pub fn remove(&mut self, id: ShapeId) -> Option<Shape> {
+        let index = self.shapes.iter().position(|(i, _, _)| *i == id)?;
+        self.version = next_revision();
+        Some(self.shapes.remove(index).2)
+    }
+
+    pub fn get(&self, id: ShapeId) -> Option<&Shape> {
+        self.shapes
+            .iter()
+            .find(|(i, _, _)| *i == id)
+            .map(|(_, _, s)| s)
+    }
+
+    /// Mutable access to a shape. The shape is assumed to have changed and
+    /// will be re-tessellated on the next update.
+    pub fn get_mut(&mut self, id: ShapeId) -> Option<&mut Shape> {
+        let (_, revision, shape) = self.shapes.iter_mut().find(|(i, _, _)| *i == id)?;
+        *revision = next_revision();
+        self.version = next_revision();
+        Some(shape)
+    }
+
+    pub fn add_group(&mut self, group: Group) -> GroupId {
+        self.groups.push(group);
+        self.group_revisions.push(next_revision());
+        self.version = next_revision();
+        GroupId(self.groups.len() as u32 - 1)
+    }
+
+    pub fn group(&self, id: GroupId) -> Option<&Group> {
+        self.groups.get(id.0 as usize)
+    }
+
+    /// Mutable access to a group, which re-tessellates its clip like
+    /// [`Scene::get_mut`], and the shapes in it if their opacity changes.
+    pub fn group_mut(&mut self, id: GroupId) -> Option<&mut Group> {
+        let group = self.groups.get_mut(id.0 as usize)?;
+        self.group_revisions[id.0 as usize] = next_revision();
+        self.version = next_revision();
+        Some(group)
+    }
+
+    /// Adds an image for [`ImagePaint`](crate::paint::ImagePaint)s to paint
+    /// with. It is uploaded to the GPU once and can't be changed afterwards.
+    pub fn add_image(&mut self, image: Image) -> ImageId {
+        let id = ImageId(NEXT_IMAGE_ID.fetch_add(1, Ordering::Relaxed));
+        self.images.push((id, image));
+        self.version = next_revision();
+        id
+    }
+
+    pub fn image(&self, id: ImageId) -> Option<&Image> {
+        self.images
+            .iter()
+            .find(|(i, _)| *i == id)
+            .map(|(_, image)| image)
+    }
+
+    /// Images in the order they were added.
+    pub fn images(&self) -> impl Iterator<Item = (ImageId, &Image)> {
+        self.images.iter().map(|(id, image)| (*id, image))
+    }
+
+    /// Removes every shape and group. Images are kept, so paints made with
+    /// them stay valid.
+    pub fn clear(&mut self) {
+        self.shapes.clear();
+        self.groups.clear();
+        self.group_revisions.clear();
+        self.version = next_revision();
+    }
+
+    pub fn len(&self) -> usize {
+        self.shapes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+
+    /// Shapes in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (ShapeId, &Shape)> {
+        self.shapes.iter().map(|(id, _, shape)| (*id, shape))
+    }
+
+    /// Changes on every change, and is never the same for two scenes, so a
+    /// renderer drawing several can tell when its buffers are stale.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Shapes in the order they should be drawn.
+    pub fn draw_order(&self) -> Vec<&Shape> {
+        self.ordered()
+            .into_iter()
+            .map(|(_, _, shape)| shape)
+            .collect()
+    }
+
+    // Shapes in drawing order, with their id and revision.
+    fn ordered(&self) -> Vec<(ShapeId, u64, &Shape)> {
+        let mut shapes: Vec<(ShapeId, u64, &Shape)> = self
+            .shapes
+            .iter()
+            .map(|(id, revision, shape)| (*id, *revision, shape))
+            .collect();
+        // sort_by_key is stable, so insertion order is kept within a z_index.
+        shapes.sort_by_key(|(_, _, shape)| shape.z_index);
+        shapes
+    }
+
+    /// The groups `shape` is in, outermost first.
+    pub fn ancestors(&self, shape: &Shape) -> Vec<GroupId> {
+        let mut groups = Vec::new();
+        let mut group = shape.group;
+        while let Some(id) = group {
+            // A cycle made through group_mut would never end otherwise.
+            if groups.contains(&id) {
+                break;
+            }
+            groups.push(id);
+            group = self.group(id).and_then(|g| g.parent);
+        }
+        groups.reverse();
+        groups
+    }
+
+    /// The opacity `shape` is drawn with: its own times that of every group
+    /// it is in.
+    pub fn opacity(&self, shape: &Shape) -> f32 {
+        let mut opacity = shape.opacity;
+        let mut group = shape.group;
+        // Bounded so a cycle made through group_mut can't hang.
+        for _ in 0..=self.groups.len() {
+            match group.and_then(|id| self.group(id)) {
+                Some(g) => {
+                    opacity *= g.opacity;
+                    group = g.parent;
+                }
+                None => break,
+            }
+        }
+        opacity
+    }
+
+    /// Tessellates every shape, fill first then stroke, with positions mapped
+    /// to scene space through the shape's transform. `pixel_scale` is the
+    /// number of device pixels per scene unit and sets the curve tolerance.
+    /// With `feathering`, fills get a strip along their outline for the
+    /// shader to fade.
+    ///
+    /// The clips of a shape's groups are drawn into the stencil buffer before
+    /// it, and taken out again once a shape outside them comes up. Instanced
+    /// shapes are tessellated once, their copies placed by the GPU. Shapes
+    /// drawn the same way share draw calls wherever that keeps the picture
+    /// the same, see [`Tessellation::finish`].
+    ///
+    /// Shapes and clips that haven't changed since the last scene
+    /// `tessellator` went through, at the same `pixel_scale`, reuse the
+    /// geometry it kept of them.
+    pub(crate) fn tessellate(
+        &self,
+        tessellator: &mut Tessellator,
+        pixel_scale: f32,
+        feathering: bool,
+        tessellation: &mut Tessellation,
+    ) {
+        // The clips in the stencil buffer, outermost first, with the indices
+        // that drew them.
+        let mut clips: Vec<(GroupId, Range<u32>)> = Vec::new();
+        // How many times each clip was drawn so far.
+        let mut clip_counts: HashMap<GroupId, u32> = HashMap::new();
+        tessellator.start();
+        for (shape_id, revision, shape) in self.ordered() {
+            let opacity = self.opacity(shape);
+            if opacity <= 0.0 {
+                continue;
+            }
+            let mut clipping: Vec<GroupId> = self
+                .ancestors(shape)
+                .into_iter()
+                .filter(|id| self.group(*id).is_some_and(|g| g.clip.is_some()))
+                .collect();
+            if clipping.len() > MAX_CLIP_DEPTH {
+                log::warn!(
+                    "Shape inside {} clips, only the outermost {} are applied",
+                    clipping.len(),
+                    MAX_CLIP_DEPTH
+                );
+                clipping.truncate(MAX_CLIP_DEPTH);
+            }
+            let common = clips
+                .iter()
+                .zip(&clipping)
+                .take_while(|((a, _), b)| a == *b)
+                .count();
+            while clips.len() > common {
+                let (_, indices) = clips.pop().unwrap();
+                tessellation.pop_clip(indices, clips.len() as u32);
+            }
+            for &id in &clipping[common..] {
+                let clip = self.group(id).and_then(|g| g.clip.as_ref()).unwrap();
+                let count = clip_counts.entry(id).or_insert(0);
+                let source = Source::Clip(id, *count);
+                *count += 1;
+                // Clips are neither feathered nor faded.
+                let key = PieceKey {
+                    revision: self.group_revisions[id.0 as usize],
+                    pixel_scale,
+                    feathering: false,
+                    opacity: 1.0,
+                };
+                let piece = match tessellator.reuse(source, &key) {
+                    Some(piece) => piece,
+                    None => tessellate_clip(tessellator, clip, pixel_scale),
+                };
+                let first_vertex = tessellation.add_vertices(source, &piece.vertices);
+                let start = tessellation.add_indices(&piece.fill, first_vertex);
+                let indices = start..tessellation.geometry.indices.len() as u32;
+                tessellator.keep(source, key, piece);
+                tessellation.push_clip(indices.clone(), clips.len() as u32);
+                clips.push((id, indices));
+            }
+            let depth = clips.len() as u32;
+
+            let instances = if shape.instances.is_empty() {
+                0..1
+            } else {
+                let start = tessellation.instances.len() as u32;
+                tessellation
+                    .instances
+                    .extend(shape.instances.iter().map(Instance::raw));
+                start..tessellation.instances.len() as u32
+            };
+
+            let source = Source::Shape(shape_id);
+            let key = PieceKey {
+                revision,
+                pixel_scale,
+                feathering,
+                opacity,
+            };
+            let piece = match tessellator.reuse(source, &key) {
+                Some(piece) => piece,
+                None => tessellate_shape(tessellator, shape, &key),
+            };
+            let first_vertex = tessellation.add_vertices(source, &piece.vertices);
+            if let Some(fill) = &shape.fill {
+                let paint = self.add_paint(&fill.paint, opacity, tessellation);
+                let start = tessellation.add_indices(&piece.fill, first_vertex);
+                let image = fill.paint.texture();
+                tessellation.push_draw(start, paint, image, depth, instances.clone());
+            }
+            if let Some(stroke) = &shape.stroke {
+                let paint = self.add_paint(&stroke.paint, opacity, tessellation);
+                let start = tessellation.add_indices(&piece.stroke, first_vertex);
+                let image = stroke.paint.texture();
+                tessellation.push_draw(start, paint, image, depth, instances.clone());
+            }
+            tessellator.keep(source, key, piece);
+        }
+        tessellation.finish();
+        log::debug!(
+            "{} shapes batched into {} draws",
+            self.shapes.len(),
+            tessellation.draws.len()
+        );
+    }
+
+    // The index of the paint uniform to draw `paint` with, adding it to
+    // `tessellation` if it needs its own. Solid colors all go through the
+    // vertices, see `vertex_color`.
+    fn add_paint(&self, paint: &Paint, opacity: f32, tessellation: &mut Tessellation) -> usize {
+        match paint {
+            Paint::Solid(_) => 0,
+            _ => tessellation.add_paint(paint.uniform(opacity, self)),
+        }
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Tessellates the fill and the stroke of `shape` for `key`.
+fn tessellate_shape(tessellator: &mut Tessellator, shape: &Shape, key: &PieceKey) -> Piece {
+    // Tessellated for the largest copy, so curves are smooth on all.
+    let instance_scale = if shape.instances.is_empty() {
+        1.0
+    } else {
+        shape
+            .instances
+            .iter()
+            .map(|i| scale_factor(&i.transform))
+            .fold(0.0, f32::max)
+    };
+    let transform = shape.transform;
+    let scale = scale_factor(&shape.transform) * instance_scale;
+    let tolerance = tolerance_for_scale(key.pixel_scale * scale);
+    let mut geometry = Geometry::new();
+    let mut piece = Piece::default();
+    if let Some(fill) = &shape.fill {
+        let options = fill.options.with_tolerance(tolerance);
+        let color = vertex_color(&fill.paint, key.opacity);
+        let primitive = shape.primitive.as_ref();
+        if let Err(e) = tessellator.fill(
+            &shape.path,
+            primitive,
+            &options,
+            color,
+            &transform,
+            &mut geometry,
+        ) {
+            log::warn!("Failed to fill shape: {:?}", e);
+        }
+        if key.feathering {
+            if let Err(e) =
+                tessellator.feather(&shape.path, tolerance, color, &transform, &mut geometry)
+            {
+                log::warn!("Failed to feather shape: {:?}", e);
+            }
+        }
+        piece.fill = std::mem::take(&mut geometry.indices);
+    }
+    if let Some(stroke) = &shape.stroke {
+        let options = stroke.options.with_tolerance(tolerance);
+        let color = vertex_color(&stroke.paint, key.opacity);
+        // Dashes are strokes of their own, cut from the outline.
+        let dashed = stroke
+            .dash
+            .as_ref()
+            .map(|d| dash(&shape.path, d, tolerance));
+        let (path, primitive) = match &dashed {
+            Some(dashed) => (dashed, None),
+            None => (&shape.path, shape.primitive.as_ref()),
+        };
+        if let Err(e) =
+            tessellator.stroke(path, primitive, &options, color, &transform, &mut geometry)
+        {
+            log::warn!("Failed to stroke shape: {:?}", e);
+        }
+        piece.stroke = std::mem::take(&mut geometry.indices);
+    }
+    piece.vertices = geometry.vertices;
+    piece
+}
+
+// Fills the paths of `clip`.
+fn tessellate_clip(tessellator: &mut Tessellator, clip: &Clip, pixel_scale: f32) -> Piece {
+    let tolerance = tolerance_for_scale(pixel_scale * scale_factor(&clip.transform));
+    let mut geometry = Geometry::new();
+    // One draw for all the paths still makes their union: a pixel raised by
+    // one no longer has the stencil value the others test for.
+    for (path, fill_rule) in clip.paths() {
+        let options = FillOptions::tolerance(tolerance).with_fill_rule(fill_rule);
+        if let Err(e) = tessellator.fill(
+            path,
+            None,
+            &options,
+            [0.0; 4],
+            &clip.transform,
+            &mut geometry,
+        ) {
+            log::warn!("Failed to fill clip: {:?}", e);
+        }
+    }
+    Piece {
+        vertices: geometry.vertices,
+        fill: geometry.indices,
+        stroke: Vec::new(),
+    }
+}
+
+// The color the vertices of a shape painted with `paint` get, premultiplied.
+// It multiplies the paint; gradients are faded in their uniform instead.
+fn vertex_color(paint: &Paint, opacity: f32) -> [f32; 4] {
+    match paint {
+        Paint::Solid(color) => premultiply(*color, opacity),
+        Paint::Image(_) => premultiply([1.0; 4], opacity),
+        _ => [1.0; 4],
+    }
+}
+
+/// Scales `color`'s alpha by `opacity` and multiplies its RGB by the result,
+/// the form the blend state expects.
+pub fn premultiply(color: [f32; 4], opacity: f32) -> [f32; 4] {
+    let alpha = (color[3] * opacity).clamp(0.0, 1.0);
+    [color[0] * alpha, color[1] * alpha, color[2] * alpha, alpha]
+}
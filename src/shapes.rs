@@ -0,0 +1,201 @@
+//! Ready-made paths and shapes.
+//!
+//! Angles are measured from the positive x axis towards the positive y axis,
+//! which is clockwise on screen since y points down.
+
+use std::f32::consts::PI;
+
+use lyon::geom::Arc;
+use lyon::math::{point, vector, Angle, Box2D, Point, Vector};
+use lyon::path::builder::BorderRadii;
+use lyon::path::{Path, Polygon, Winding};
+
+use crate::scene::Shape;
+use crate::svg::parse_path;
+
+/// An outline lyon can tessellate without building a path first. Filling a
+/// rectangle or a circle also skips the sweep-line algorithm; ellipses and
+/// polygons still go through it, ellipses without the self-intersection
+/// checks.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Primitive {
+    Rect(Box2D),
+    Circle {
+        center: Point,
+        radius: f32,
+    },
+    Ellipse {
+        center: Point,
+        radii: Vector,
+        rotation: Angle,
+    },
+    /// A closed polygon through the points, in order.
+    Polygon(Vec<Point>),
+}
+
+impl Primitive {
+    /// The same outline as a path, winding clockwise on screen.
+    pub fn to_path(&self) -> Path {
+        let mut builder = Path::builder();
+        match self {
+            Primitive::Rect(rect) => builder.add_rectangle(rect, Winding::Positive),
+            Primitive::Circle { center, radius } => {
+                builder.add_circle(*center, *radius, Winding::Positive)
+            }
+            Primitive::Ellipse {
+                center,
+                radii,
+                rotation,
+            } => builder.add_ellipse(*center, *radii, *rotation, Winding::Positive),
+            Primitive::Polygon(points) => builder.add_polygon(Polygon {
+                points,
+                closed: true,
+            }),
+        };
+        builder.build()
+    }
+}
+
+/// The arrow from the first version of the renderer, pointing right. It spans
+/// `-1.0..1.5` horizontally and `-0.7..0.7` vertically.
+pub fn arrow() -> Path {
+    parse_path("M-1 -0.2 H0.5 V-0.7 L1.5 0 L0.5 0.7 V0.2 H-1 Z").unwrap()
+}
+
+pub fn rect(rect: Box2D) -> Shape {
+    Shape::from_primitive(Primitive::Rect(rect))
+}
+
+/// A rectangle with each corner rounded by its own radius. Radii too large
+/// for the rectangle are all scaled down by the same factor, the way CSS
+/// does, so that those along each side add up to at most its length.
+pub fn rounded_rect(rect: Box2D, radii: &BorderRadii) -> Shape {
+    let BorderRadii {
+        top_left,
+        top_right,
+        bottom_left,
+        bottom_right,
+    } = *radii;
+    if top_left <= 0.0 && top_right <= 0.0 && bottom_left <= 0.0 && bottom_right <= 0.0 {
+        return self::rect(rect);
+    }
+    let (width, height) = (rect.width(), rect.height());
+    let scale = [
+        width / (top_left + top_right),
+        width / (bottom_left + bottom_right),
+        height / (top_left + bottom_left),
+        height / (top_right + bottom_right),
+    ]
+    .into_iter()
+    .filter(|f| f.is_finite() && *f >= 0.0)
+    .fold(1.0f32, f32::min);
+    let radii = BorderRadii {
+        top_left: top_left * scale,
+        top_right: top_right * scale,
+        bottom_left: bottom_left * scale,
+        bottom_right: bottom_right * scale,
+    };
+    let mut builder = Path::builder();
+    builder.add_rounded_rectangle(&rect, &radii, Winding::Positive);
+    Shape::new(builder.build())
+}
+
+pub fn circle(center: Point, radius: f32) -> Shape {
+    Shape::from_primitive(Primitive::Circle { center, radius })
+}
+
+/// An ellipse with horizontal and vertical radii `radii`, turned by
+/// `rotation` around its center.
+pub fn ellipse(center: Point, radii: Vector, rotation: Angle) -> Shape {
+    Shape::from_primitive(Primitive::Ellipse {
+        center,
+        radii,
+        rotation,
+    })
+}
+
+/// A polygon with `sides` equal sides and its corners on a circle of
+/// `radius`, the first one straight above the center. Less than three sides
+/// make an empty shape.
+pub fn regular_polygon(center: Point, radius: f32, sides: usize) -> Shape {
+    let points = (0..sides)
+        .map(|i| on_circle(center, radius, corner_angle(i, sides)))
+        .collect();
+    Shape::from_primitive(Primitive::Polygon(points))
+}
+
+/// A star with `points` tips on a circle of `outer_radius`, the first one
+/// straight above the center, and the corners between them on a circle of
+/// `inner_radius`.
+pub fn star(center: Point, outer_radius: f32, inner_radius: f32, points: usize) -> Shape {
+    let corners = (0..points * 2)
+        .map(|i| {
+            let radius = if i % 2 == 0 {
+                outer_radius
+            } else {
+                inner_radius
+            };
+            on_circle(center, radius, corner_angle(i, points * 2))
+        })
+        .collect();
+    Shape::from_primitive(Primitive::Polygon(corners))
+}
+
+/// The part of a circle from `start` going `sweep` around, as an open path.
+/// It is meant to be stroked; a fill covers the area between the arc and
+/// its chord.
+pub fn arc(center: Point, radius: f32, start: Angle, sweep: Angle) -> Shape {
+    let mut builder = Path::builder();
+    let arc = circle_arc(center, radius, start, sweep);
+    builder.begin(arc.from());
+    arc.for_each_cubic_bezier(&mut |curve| {
+        builder.cubic_bezier_to(curve.ctrl1, curve.ctrl2, curve.to);
+    });
+    builder.end(false);
+    Shape::new(builder.build())
+}
+
+/// A slice of a circle from `start` going `sweep` around, closed through the
+/// center.
+pub fn pie(center: Point, radius: f32, start: Angle, sweep: Angle) -> Shape {
+    let mut builder = Path::builder();
+    let arc = circle_arc(center, radius, start, sweep);
+    builder.begin(center);
+    builder.line_to(arc.from());
+    arc.for_each_cubic_bezier(&mut |curve| {
+        builder.cubic_bezier_to(curve.ctrl1, curve.ctrl2, curve.to);
+    });
+    builder.end(true);
+    Shape::new(builder.build())
+}
+
+/// The area between two circles around `center`. The inner one winds the
+/// other way, so it is a hole with either fill rule.
+pub fn ring(center: Point, outer_radius: f32, inner_radius: f32) -> Shape {
+    let mut builder = Path::builder();
+    builder.add_circle(center, outer_radius, Winding::Positive);
+    builder.add_circle(center, inner_radius, Winding::Negative);
+    Shape::new(builder.build())
+}
+
+fn circle_arc(center: Point, radius: f32, start: Angle, sweep: Angle) -> Arc<f32> {
+    Arc {
+        center,
+        radii: vector(radius, radius),
+        start_angle: start,
+        sweep_angle: sweep,
+        x_rotation: Angle::zero(),
+    }
+}
+
+// The angle of the `i`th of `count` corners spread evenly from the top.
+fn corner_angle(i: usize, count: usize) -> f32 {
+    -PI / 2.0 + 2.0 * PI * i as f32 / count as f32
+}
+
+fn on_circle(center: Point, radius: f32, angle: f32) -> Point {
+    point(
+        center.x + radius * angle.cos(),
+        center.y + radius * angle.sin(),
+    )
+}
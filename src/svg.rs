@@ -0,0 +1,9 @@
+//! Reading and writing SVG paths and scenes.
+
+mod export;
+mod import;
+mod path;
+
+pub use export::{export, path_data};
+pub use import::{import, ImportError};
+pub use path::{parse_path, PathError, PathErrorKind};
@@ -0,0 +1,318 @@
+//! Writing a scene out as an SVG document.
+
+use std::fmt::Write;
+
+use lyon::math::{Size, Transform};
+use lyon::path::{Event as PathEvent, Path};
+use lyon::tessellation::{FillRule, LineCap, LineJoin, StrokeOptions};
+
+use crate::paint::{GradientStop, Paint, Spread};
+use crate::scene::{Clip, GroupId, Scene, Shape};
+
+/// Writes the shapes of `scene` as an SVG document of the given size, in
+/// drawing order. Groups become `<g>` elements, split where shapes of other
+/// groups are drawn in between, and gradients and clips go in `<defs>`.
+///
+/// Colors are written in hex when that is exact, and as percentages with
+/// all the precision they need otherwise, so importing the document gives
+/// them back unchanged. Instanced shapes are written once per copy. Image paints and the background aren't
+/// written.
+pub fn export(scene: &Scene, size: Size) -> String {
+    let mut exporter = Exporter::default();
+    let mut open: Vec<GroupId> = Vec::new();
+    for shape in scene.draw_order() {
+        let groups = scene.ancestors(shape);
+        let common = open.iter().zip(&groups).take_while(|(a, b)| a == b).count();
+        while open.len() > common {
+            open.pop();
+            exporter.line(open.len(), "</g>");
+        }
+        for &id in &groups[common..] {
+            let mut element = "<g".to_string();
+            if let Some(group) = scene.group(id) {
+                attribute(&mut element, "opacity", group.opacity, 1.0);
+                if let Some(clip) = &group.clip {
+                    let clip_id = exporter.clip(clip);
+                    let _ = write!(element, " clip-path=\"url(#{})\"", clip_id);
+                }
+            }
+            element.push('>');
+            exporter.line(open.len(), &element);
+            open.push(id);
+        }
+        if shape.instances.is_empty() {
+            exporter.shape(open.len(), shape);
+        }
+        // Each copy of an instanced shape is written out on its own.
+        for instance in &shape.instances {
+            let mut copy = shape.clone();
+            copy.transform = shape.transform.then(&instance.transform);
+            copy.instances.clear();
+            if let Some(fill) = &mut copy.fill {
+                fill.paint = fill.paint.tinted(instance.tint);
+            }
+            if let Some(stroke) = &mut copy.stroke {
+                stroke.paint = stroke.paint.tinted(instance.tint);
+            }
+            exporter.shape(open.len(), &copy);
+        }
+    }
+    while !open.is_empty() {
+        open.pop();
+        exporter.line(open.len(), "</g>");
+    }
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+        size.width, size.height
+    );
+    if !exporter.defs.is_empty() {
+        svg.push_str("  <defs>\n");
+        svg.push_str(&exporter.defs);
+        svg.push_str("  </defs>\n");
+    }
+    svg.push_str(&exporter.body);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[derive(Default)]
+struct Exporter {
+    defs: String,
+    body: String,
+    gradients: usize,
+    clips: usize,
+}
+
+impl Exporter {
+    // Adds `text` to the body on its own line, indented for `depth` open
+    // groups.
+    fn line(&mut self, depth: usize, text: &str) {
+        for _ in 0..=depth {
+            self.body.push_str("  ");
+        }
+        self.body.push_str(text);
+        self.body.push('\n');
+    }
+
+    fn shape(&mut self, depth: usize, shape: &Shape) {
+        let mut element = format!("<path d=\"{}\"", path_data(&shape.path));
+        match &shape.fill {
+            Some(fill) => {
+                self.paint(&mut element, "fill", &fill.paint);
+                if fill.options.fill_rule == FillRule::EvenOdd {
+                    element.push_str(" fill-rule=\"evenodd\"");
+                }
+            }
+            None => element.push_str(" fill=\"none\""),
+        }
+        if let Some(stroke) = &shape.stroke {
+            self.paint(&mut element, "stroke", &stroke.paint);
+            stroke_attributes(&mut element, &stroke.options);
+            if let Some(dash) = &stroke.dash {
+                let dashes: Vec<String> = dash.dashes.iter().map(f32::to_string).collect();
+                let _ = write!(element, " stroke-dasharray=\"{}\"", dashes.join(" "));
+                attribute(&mut element, "stroke-dashoffset", dash.offset, 0.0);
+            }
+        }
+        if shape.transform != Transform::identity() {
+            let _ = write!(element, " transform=\"{}\"", matrix(&shape.transform));
+        }
+        attribute(&mut element, "opacity", shape.opacity, 1.0);
+        element.push_str("/>");
+        self.line(depth, &element);
+    }
+
+    // Writes `paint` as the `name` property, adding gradients to the defs.
+    fn paint(&mut self, element: &mut String, name: &str, paint: &Paint) {
+        match paint {
+            Paint::Solid(color) => {
+                let _ = write!(element, " {}=\"{}\"", name, css_color(color));
+                attribute(element, &format!("{}-opacity", name), color[3], 1.0);
+            }
+            Paint::LinearGradient(gradient) => {
+                let id = self.gradient_id();
+                let _ = write!(
+                    self.defs,
+                    "    <linearGradient id=\"{}\" gradientUnits=\"userSpaceOnUse\" \
+                     x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"",
+                    id, gradient.start.x, gradient.start.y, gradient.end.x, gradient.end.y
+                );
+                self.gradient_end(
+                    "linearGradient",
+                    gradient.spread,
+                    &gradient.transform,
+                    &gradient.stops,
+                );
+                let _ = write!(element, " {}=\"url(#{})\"", name, id);
+            }
+            Paint::RadialGradient(gradient) => {
+                let id = self.gradient_id();
+                let _ = write!(
+                    self.defs,
+                    "    <radialGradient id=\"{}\" gradientUnits=\"userSpaceOnUse\" \
+                     cx=\"{}\" cy=\"{}\" r=\"{}\" fx=\"{}\" fy=\"{}\"",
+                    id,
+                    gradient.center.x,
+                    gradient.center.y,
+                    gradient.radius,
+                    gradient.focal.x,
+                    gradient.focal.y
+                );
+                self.gradient_end(
+                    "radialGradient",
+                    gradient.spread,
+                    &gradient.transform,
+                    &gradient.stops,
+                );
+                let _ = write!(element, " {}=\"url(#{})\"", name, id);
+            }
+            Paint::Image(_) => {
+                log::warn!(
+                    "Image paints can't be exported to SVG, leaving a {} out",
+                    name
+                );
+                let _ = write!(element, " {}=\"none\"", name);
+            }
+        }
+    }
+
+    // Adds `clip` to the defs, returning its id.
+    fn clip(&mut self, clip: &Clip) -> String {
+        self.clips += 1;
+        let id = format!("clip{}", self.clips);
+        let _ = write!(self.defs, "    <clipPath id=\"{}\"", id);
+        if clip.transform != Transform::identity() {
+            let _ = write!(self.defs, " transform=\"{}\"", matrix(&clip.transform));
+        }
+        self.defs.push_str(">\n");
+        for (path, fill_rule) in clip.paths() {
+            let _ = write!(self.defs, "      <path d=\"{}\"", path_data(path));
+            if fill_rule == FillRule::EvenOdd {
+                self.defs.push_str(" clip-rule=\"evenodd\"");
+            }
+            self.defs.push_str("/>\n");
+        }
+        self.defs.push_str("    </clipPath>\n");
+        id
+    }
+
+    fn gradient_id(&mut self) -> String {
+        self.gradients += 1;
+        format!("gradient{}", self.gradients)
+    }
+
+    // The rest of a gradient element whose start tag is open in the defs.
+    fn gradient_end(
+        &mut self,
+        tag: &str,
+        spread: Spread,
+        transform: &Transform,
+        stops: &[GradientStop],
+    ) {
+        match spread {
+            Spread::Pad => {}
+            Spread::Repeat => self.defs.push_str(" spreadMethod=\"repeat\""),
+            Spread::Reflect => self.defs.push_str(" spreadMethod=\"reflect\""),
+        }
+        if *transform != Transform::identity() {
+            let _ = write!(self.defs, " gradientTransform=\"{}\"", matrix(transform));
+        }
+        self.defs.push_str(">\n");
+        for stop in stops {
+            let _ = write!(
+                self.defs,
+                "      <stop offset=\"{}\" stop-color=\"{}\"",
+                stop.offset,
+                css_color(&stop.color)
+            );
+            attribute(&mut self.defs, "stop-opacity", stop.color[3], 1.0);
+            self.defs.push_str("/>\n");
+        }
+        let _ = writeln!(self.defs, "    </{}>", tag);
+    }
+}
+
+fn stroke_attributes(element: &mut String, options: &StrokeOptions) {
+    attribute(
+        element,
+        "stroke-width",
+        options.line_width,
+        StrokeOptions::DEFAULT_LINE_WIDTH,
+    );
+    if options.start_cap != options.end_cap {
+        log::warn!("SVG has a single line cap for both ends, using the start one");
+    }
+    match options.start_cap {
+        LineCap::Butt => {}
+        LineCap::Square => element.push_str(" stroke-linecap=\"square\""),
+        LineCap::Round => element.push_str(" stroke-linecap=\"round\""),
+    }
+    match options.line_join {
+        LineJoin::Miter => {}
+        LineJoin::MiterClip => element.push_str(" stroke-linejoin=\"miter-clip\""),
+        LineJoin::Round => element.push_str(" stroke-linejoin=\"round\""),
+        LineJoin::Bevel => element.push_str(" stroke-linejoin=\"bevel\""),
+    }
+    attribute(
+        element,
+        "stroke-miterlimit",
+        options.miter_limit,
+        StrokeOptions::DEFAULT_MITER_LIMIT,
+    );
+}
+
+// Writes ` name="value"` unless `value` is the default.
+fn attribute(element: &mut String, name: &str, value: f32, default: f32) {
+    if value != default {
+        let _ = write!(element, " {}=\"{}\"", name, value);
+    }
+}
+
+/// The path as SVG path data, with absolute commands only. Numbers are
+/// written in full, so [`parse_path`](super::parse_path) gives the same path
+/// back.
+pub fn path_data(path: &Path) -> String {
+    let mut d = String::new();
+    for event in path.iter() {
+        if !d.is_empty() && !matches!(event, PathEvent::End { close: false, .. }) {
+            d.push(' ');
+        }
+        let _ = match event {
+            PathEvent::Begin { at } => write!(d, "M{} {}", at.x, at.y),
+            PathEvent::Line { to, .. } => write!(d, "L{} {}", to.x, to.y),
+            PathEvent::Quadratic { ctrl, to, .. } => {
+                write!(d, "Q{} {} {} {}", ctrl.x, ctrl.y, to.x, to.y)
+            }
+            PathEvent::Cubic {
+                ctrl1, ctrl2, to, ..
+            } => write!(
+                d,
+                "C{} {} {} {} {} {}",
+                ctrl1.x, ctrl1.y, ctrl2.x, ctrl2.y, to.x, to.y
+            ),
+            PathEvent::End { close: true, .. } => write!(d, "Z"),
+            PathEvent::End { close: false, .. } => Ok(()),
+        };
+    }
+    d
+}
+
+fn matrix(t: &Transform) -> String {
+    format!(
+        "matrix({} {} {} {} {} {})",
+        t.m11, t.m12, t.m21, t.m22, t.m31, t.m32
+    )
+}
+
+// The RGB of `color`, without its alpha, which goes in an opacity property.
+fn css_color(color: &[f32; 4]) -> String {
+    let rgb = [color[0], color[1], color[2]].map(|c| c.clamp(0.0, 1.0));
+    let bytes = rgb.map(|c| (c * 255.0).round() as u8);
+    if rgb.iter().zip(bytes).all(|(&c, b)| b as f32 / 255.0 == c) {
+        return format!("#{:02x}{:02x}{:02x}", bytes[0], bytes[1], bytes[2]);
+    }
+    // Exact in f64, and read back as such by the importer.
+    let percent = rgb.map(|c| c as f64 * 100.0);
+    format!("rgb({}%, {}%, {}%)", percent[0], percent[1], percent[2])
+}
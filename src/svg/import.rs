@@ -0,0 +1,861 @@
+//! Loading a subset of SVG documents into a scene.
+//!
+//! Supported are `<path>`, `<rect>`, `<circle>`, `<ellipse>`, `<line>`,
+//! `<polyline>`, `<polygon>` and `<g>`, with `transform`, `fill`, `stroke`,
+//! `stroke-width`, `opacity` and `fill-rule`, plus a few neighbours of
+//! those like `fill-opacity` and `stroke-linejoin`. Properties can be given
+//! as attributes or in a `style` attribute. Fills and strokes can also
+//! reference a `<linearGradient>` or `<radialGradient>`. Anything else is
+//! skipped with a warning, except for elements that don't draw anything,
+//! like `<title>`.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use lyon::algorithms::aabb::bounding_box;
+use lyon::geom::{Angle, ArcFlags};
+use lyon::math::{point, size, vector, Box2D, Size, Transform};
+use lyon::path::builder::SvgPathBuilder;
+use lyon::path::{Path, Winding};
+use lyon::tessellation::{FillOptions, FillRule, LineCap, LineJoin, StrokeOptions};
+use roxmltree::Node;
+
+use super::path::{is_whitespace, parse_path, scan_number};
+use crate::canvas::parse_color;
+use crate::dash::DashPattern;
+use crate::paint::{GradientStop, LinearGradient, Paint, RadialGradient, Spread};
+use crate::scene::{Clip, FillStyle, Group, GroupId, Scene, Shape, StrokeStyle};
+
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+
+#[derive(Debug)]
+pub enum ImportError {
+    Xml(roxmltree::Error),
+    /// The root element isn't `<svg>`.
+    NotSvg,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportError::Xml(e) => write!(f, "invalid XML: {}", e),
+            ImportError::NotSvg => write!(f, "not an SVG document"),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Adds the shapes of the SVG document in `text` to `scene`, each `<g>` as a
+/// [`Group`], in document order. Coordinates are mapped through the root's
+/// `viewBox` so that one scene unit is one CSS pixel of the document.
+///
+/// Returns the document's size, from its `width` and `height` or else its
+/// `viewBox`, or zero when it gives neither.
+pub fn import(text: &str, scene: &mut Scene) -> Result<Size, ImportError> {
+    let options = roxmltree::ParsingOptions {
+        // Editors often write a DOCTYPE.
+        allow_dtd: true,
+        ..Default::default()
+    };
+    let document =
+        roxmltree::Document::parse_with_options(text, options).map_err(ImportError::Xml)?;
+    let root = document.root_element();
+    if !is_svg(root, "svg") {
+        return Err(ImportError::NotSvg);
+    }
+
+    let (size, transform) = viewport(root);
+    // Gradients can be referenced from anywhere in the document, usually
+    // from inside <defs>.
+    let gradients = document
+        .descendants()
+        .filter(|node| is_svg(*node, "linearGradient") || is_svg(*node, "radialGradient"))
+        .filter_map(|node| Some((node.attribute("id")?, node)))
+        .collect();
+    let clip_paths = document
+        .descendants()
+        .filter(|node| is_svg(*node, "clipPath"))
+        .filter_map(|node| Some((node.attribute("id")?, node)))
+        .collect();
+    let mut importer = Importer {
+        scene,
+        gradients,
+        clip_paths,
+    };
+    let style = Style::default().inherit(root, &importer.gradients);
+    // The root can be made translucent too.
+    let group = root.attribute("opacity").map(|_| {
+        importer
+            .scene
+            .add_group(Group::new().with_opacity(opacity(root)))
+    });
+    importer.children(root, &transform, &style, group);
+    Ok(size)
+}
+
+// The document size and the transform from the viewBox to it.
+fn viewport(root: Node) -> (Size, Transform) {
+    let width = root.attribute("width").and_then(|w| length(w, "width"));
+    let height = root.attribute("height").and_then(|h| length(h, "height"));
+    let view_box = root
+        .attribute("viewBox")
+        .and_then(|v| match numbers(v).as_deref() {
+            Some(&[x, y, w, h]) if w > 0.0 && h > 0.0 => {
+                Some(Box2D::new(point(x, y), point(x + w, y + h)))
+            }
+            _ => {
+                log::warn!("Ignoring invalid viewBox {:?}", v);
+                None
+            }
+        });
+    let Some(view_box) = view_box else {
+        return (
+            size(width.unwrap_or(0.0), height.unwrap_or(0.0)),
+            Transform::identity(),
+        );
+    };
+
+    let size = size(
+        width.unwrap_or(view_box.width()),
+        height.unwrap_or(view_box.height()),
+    );
+    let sx = size.width / view_box.width();
+    let sy = size.height / view_box.height();
+    let to_origin = Transform::translation(-view_box.min.x, -view_box.min.y);
+    let transform = match root.attribute("preserveAspectRatio").map(str::trim) {
+        Some("none") => to_origin.then_scale(sx, sy),
+        aspect => {
+            if !matches!(aspect, None | Some("xMidYMid") | Some("xMidYMid meet")) {
+                log::warn!("Only preserveAspectRatio=\"xMidYMid meet\" or \"none\" are supported");
+            }
+            // Scaled uniformly to fit, and centered.
+            let scale = sx.min(sy);
+            let offset = vector(
+                (size.width - view_box.width() * scale) / 2.0,
+                (size.height - view_box.height() * scale) / 2.0,
+            );
+            to_origin.then_scale(scale, scale).then_translate(offset)
+        }
+    };
+    (size, transform)
+}
+
+// A fill or stroke, before it is known what it paints.
+#[derive(Clone, Debug)]
+enum StylePaint {
+    Paint(Paint),
+    // A gradient with gradientUnits="objectBoundingBox", in units of the
+    // painted shape's bounding box.
+    BoundingBox(Paint),
+}
+
+impl StylePaint {
+    // The paint for `path`, with `opacity` applied.
+    fn resolve(&self, path: &Path, opacity: f32) -> Paint {
+        let paint = match self {
+            StylePaint::Paint(paint) => paint.clone(),
+            StylePaint::BoundingBox(paint) => {
+                let bounds = bounding_box(path.iter());
+                let to_bounds = Transform::scale(bounds.width(), bounds.height())
+                    .then_translate(bounds.min.to_vector());
+                paint.transformed(&to_bounds)
+            }
+        };
+        if opacity == 1.0 {
+            return paint;
+        }
+        match paint {
+            Paint::Solid([r, g, b, a]) => Paint::Solid([r, g, b, a * opacity]),
+            Paint::LinearGradient(mut gradient) => {
+                fade_stops(&mut gradient.stops, opacity);
+                Paint::LinearGradient(gradient)
+            }
+            Paint::RadialGradient(mut gradient) => {
+                fade_stops(&mut gradient.stops, opacity);
+                Paint::RadialGradient(gradient)
+            }
+            paint => paint,
+        }
+    }
+}
+
+fn fade_stops(stops: &mut [GradientStop], opacity: f32) {
+    for stop in stops {
+        stop.color[3] *= opacity;
+    }
+}
+
+type Gradients<'a, 'input> = HashMap<&'a str, Node<'a, 'input>>;
+
+// The inherited properties, as computed for an element.
+#[derive(Clone, Debug)]
+struct Style {
+    fill: Option<StylePaint>,
+    fill_opacity: f32,
+    fill_rule: FillRule,
+    stroke: Option<StylePaint>,
+    stroke_opacity: f32,
+    stroke_width: f32,
+    line_cap: LineCap,
+    line_join: LineJoin,
+    miter_limit: f32,
+    dash_array: Option<Vec<f32>>,
+    dash_offset: f32,
+}
+
+impl Default for Style {
+    // The initial values from the SVG specification.
+    fn default() -> Self {
+        Self {
+            fill: Some(StylePaint::Paint(Paint::Solid([0.0, 0.0, 0.0, 1.0]))),
+            fill_opacity: 1.0,
+            fill_rule: FillRule::NonZero,
+            stroke: None,
+            stroke_opacity: 1.0,
+            stroke_width: 1.0,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter,
+            miter_limit: 4.0,
+            dash_array: None,
+            dash_offset: 0.0,
+        }
+    }
+}
+
+impl Style {
+    // This style with the properties `node` sets. Invalid values are warned
+    // about and leave the inherited value, which is close to what browsers
+    // do.
+    fn inherit(&self, node: Node, gradients: &Gradients) -> Style {
+        let mut style = self.clone();
+        if let Some(paint) = property(node, "fill").and_then(|v| parse_paint(v, "fill", gradients))
+        {
+            style.fill = paint;
+        }
+        if let Some(paint) =
+            property(node, "stroke").and_then(|v| parse_paint(v, "stroke", gradients))
+        {
+            style.stroke = paint;
+        }
+        if let Some(value) = property(node, "fill-opacity").and_then(|v| number(v, "fill-opacity"))
+        {
+            style.fill_opacity = value.clamp(0.0, 1.0);
+        }
+        if let Some(value) =
+            property(node, "stroke-opacity").and_then(|v| number(v, "stroke-opacity"))
+        {
+            style.stroke_opacity = value.clamp(0.0, 1.0);
+        }
+        if let Some(value) = property(node, "stroke-width").and_then(|v| length(v, "stroke-width"))
+        {
+            if value >= 0.0 {
+                style.stroke_width = value;
+            }
+        }
+        match property(node, "fill-rule") {
+            Some("nonzero") => style.fill_rule = FillRule::NonZero,
+            Some("evenodd") => style.fill_rule = FillRule::EvenOdd,
+            Some(value) => log::warn!("Ignoring invalid fill-rule {:?}", value),
+            None => {}
+        }
+        match property(node, "stroke-linecap") {
+            Some("butt") => style.line_cap = LineCap::Butt,
+            Some("round") => style.line_cap = LineCap::Round,
+            Some("square") => style.line_cap = LineCap::Square,
+            Some(value) => log::warn!("Ignoring invalid stroke-linecap {:?}", value),
+            None => {}
+        }
+        match property(node, "stroke-linejoin") {
+            Some("miter") => style.line_join = LineJoin::Miter,
+            Some("miter-clip") => style.line_join = LineJoin::MiterClip,
+            Some("round") => style.line_join = LineJoin::Round,
+            Some("bevel") => style.line_join = LineJoin::Bevel,
+            Some(value) => log::warn!("Ignoring invalid stroke-linejoin {:?}", value),
+            None => {}
+        }
+        if let Some(value) =
+            property(node, "stroke-miterlimit").and_then(|v| number(v, "stroke-miterlimit"))
+        {
+            if value >= StrokeOptions::MINIMUM_MITER_LIMIT {
+                style.miter_limit = value;
+            }
+        }
+        match property(node, "stroke-dasharray") {
+            Some("none") => style.dash_array = None,
+            Some(value) => match numbers(value) {
+                Some(dashes) if dashes.iter().all(|d| *d >= 0.0) => style.dash_array = Some(dashes),
+                _ => log::warn!("Ignoring invalid stroke-dasharray {:?}", value),
+            },
+            None => {}
+        }
+        if let Some(value) =
+            property(node, "stroke-dashoffset").and_then(|v| length(v, "stroke-dashoffset"))
+        {
+            style.dash_offset = value;
+        }
+        style
+    }
+}
+
+struct Importer<'a, 'd, 'input> {
+    scene: &'a mut Scene,
+    gradients: Gradients<'d, 'input>,
+    clip_paths: HashMap<&'d str, Node<'d, 'input>>,
+}
+
+impl Importer<'_, '_, '_> {
+    fn children(
+        &mut self,
+        parent: Node,
+        transform: &Transform,
+        style: &Style,
+        group: Option<GroupId>,
+    ) {
+        for node in parent.children().filter(Node::is_element) {
+            self.element(node, transform, style, group);
+        }
+    }
+
+    fn element(
+        &mut self,
+        node: Node,
+        transform: &Transform,
+        style: &Style,
+        group: Option<GroupId>,
+    ) {
+        let namespace = node.tag_name().namespace();
+        if namespace.is_some_and(|ns| ns != SVG_NAMESPACE) {
+            // Editor metadata, like Inkscape's.
+            return;
+        }
+        let name = node.tag_name().name();
+        if matches!(
+            name,
+            "defs" | "title" | "desc" | "metadata" | "style" | "clipPath"
+        ) {
+            return;
+        }
+
+        let transform = match node.attribute("transform") {
+            Some(value) => parse_transform(value).then(transform),
+            None => *transform,
+        };
+        let style = style.inherit(node, &self.gradients);
+        let clip = property(node, "clip-path").and_then(|value| self.clip(value, &transform));
+        if name == "g" {
+            let mut g = Group::new().with_opacity(opacity(node));
+            g.parent = group;
+            g.clip = clip;
+            let id = self.scene.add_group(g);
+            self.children(node, &transform, &style, Some(id));
+            return;
+        }
+
+        let Some(path) = element_path(node) else {
+            return;
+        };
+        // A clipped shape gets a group of its own to carry the clip.
+        let group = match clip {
+            Some(clip) => {
+                let mut g = Group::new().with_clip(clip);
+                g.parent = group;
+                Some(self.scene.add_group(g))
+            }
+            None => group,
+        };
+
+        let mut shape = Shape::new(path).with_transform(transform);
+        shape.group = group;
+        shape.opacity = opacity(node);
+        if let Some(paint) = &style.fill {
+            shape.fill = Some(FillStyle {
+                paint: paint.resolve(&shape.path, style.fill_opacity),
+                options: FillOptions::default().with_fill_rule(style.fill_rule),
+            });
+        }
+        if let Some(paint) = style.stroke.as_ref().filter(|_| style.stroke_width > 0.0) {
+            shape.stroke = Some(StrokeStyle {
+                paint: paint.resolve(&shape.path, style.stroke_opacity),
+                options: StrokeOptions::default()
+                    .with_line_width(style.stroke_width)
+                    .with_line_cap(style.line_cap)
+                    .with_line_join(style.line_join)
+                    .with_miter_limit(style.miter_limit),
+                dash: style
+                    .dash_array
+                    .as_ref()
+                    .map(|dashes| DashPattern::new(dashes).with_offset(style.dash_offset)),
+            });
+        }
+        self.scene.add(shape);
+    }
+
+    // The clip a clip-path property refers to, for an element drawn with
+    // `transform`: the union of the shapes in the <clipPath>, each filled
+    // with its own clip-rule.
+    fn clip(&self, value: &str, transform: &Transform) -> Option<Clip> {
+        if value == "none" {
+            return None;
+        }
+        let node = value
+            .strip_prefix("url(#")
+            .and_then(|v| v.strip_suffix(')'))
+            .and_then(|id| self.clip_paths.get(id.trim()));
+        let Some(node) = node else {
+            log::warn!(
+                "Ignoring clip-path {:?}, only url(#id) of a <clipPath> is supported",
+                value
+            );
+            return None;
+        };
+        if node.attribute("clipPathUnits") == Some("objectBoundingBox") {
+            log::warn!("Ignoring clip-path with clipPathUnits=\"objectBoundingBox\"");
+            return None;
+        }
+        let transform = match node.attribute("transform") {
+            Some(value) => parse_transform(value).then(transform),
+            None => *transform,
+        };
+        let mut paths = Vec::new();
+        for child in node.children().filter(Node::is_element) {
+            let Some(path) = element_path(child) else {
+                continue;
+            };
+            let path = match child.attribute("transform") {
+                Some(value) => path.transformed(&parse_transform(value)),
+                None => path,
+            };
+            let rule = property(child, "clip-rule").or_else(|| property(*node, "clip-rule"));
+            let fill_rule = match rule {
+                Some("evenodd") => FillRule::EvenOdd,
+                _ => FillRule::NonZero,
+            };
+            paths.push((path, fill_rule));
+        }
+        // An empty <clipPath> clips everything away, as it should.
+        let mut paths = paths.into_iter();
+        let (path, fill_rule) = paths.next().unwrap_or((Path::new(), FillRule::NonZero));
+        let mut clip = Clip::new(path)
+            .with_transform(transform)
+            .with_fill_rule(fill_rule);
+        clip.union.extend(paths);
+        Some(clip)
+    }
+}
+
+// The outline of a basic shape or <path> element, None for other elements
+// or invalid ones.
+fn element_path(node: Node) -> Option<Path> {
+    match node.tag_name().name() {
+        "path" => node.attribute("d").and_then(|d| match parse_path(d) {
+            Ok(path) => Some(path),
+            Err(e) => {
+                log::warn!("Skipping <path> with invalid data: {}", e);
+                None
+            }
+        }),
+        "rect" => rect(node),
+        "circle" => circle(node),
+        "ellipse" => ellipse(node),
+        "line" => line(node),
+        "polyline" => poly(node, false),
+        "polygon" => poly(node, true),
+        name => {
+            log::warn!("Skipping unsupported <{}> element", name);
+            None
+        }
+    }
+}
+
+fn is_svg(node: Node, name: &str) -> bool {
+    let tag = node.tag_name();
+    tag.name() == name && tag.namespace().is_none_or(|ns| ns == SVG_NAMESPACE)
+}
+
+// A property from the style attribute, which wins, or from the attribute of
+// the same name.
+fn property<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    let from_style = node.attribute("style").and_then(|style| {
+        style
+            .split(';')
+            .rev()
+            .filter_map(|declaration| declaration.split_once(':'))
+            .filter(|(key, _)| key.trim() == name)
+            .map(|(_, value)| value.trim())
+            .next()
+    });
+    from_style
+        .or_else(|| node.attribute(name))
+        .map(|value| value.trim_matches(is_whitespace))
+        .filter(|value| *value != "inherit")
+}
+
+fn opacity(node: Node) -> f32 {
+    property(node, "opacity")
+        .and_then(|v| number(v, "opacity"))
+        .map_or(1.0, |o| o.clamp(0.0, 1.0))
+}
+
+// Some(None) for "none", None if `value` isn't a paint this supports.
+fn parse_paint(value: &str, name: &str, gradients: &Gradients) -> Option<Option<StylePaint>> {
+    if value == "none" {
+        return Some(None);
+    }
+    if let Some(rest) = value.strip_prefix("url(") {
+        let (reference, fallback) = rest.split_once(')')?;
+        let id = reference
+            .trim()
+            .trim_matches(['"', '\''])
+            .strip_prefix('#')?;
+        if let Some(node) = gradients.get(id) {
+            return Some(Some(parse_gradient(*node, gradients)));
+        }
+        // Used when the reference is missing.
+        let fallback = fallback.trim_matches(is_whitespace);
+        if fallback.is_empty() {
+            log::warn!("No gradient with id {:?} for {}", id, name);
+            return Some(None);
+        }
+        return parse_paint(fallback, name, gradients);
+    }
+    match parse_svg_color(value) {
+        Some(color) => Some(Some(StylePaint::Paint(Paint::Solid(color)))),
+        None => {
+            log::warn!("Ignoring unsupported {} {:?}", name, value);
+            None
+        }
+    }
+}
+
+fn parse_gradient(node: Node, gradients: &Gradients) -> StylePaint {
+    // Stops can come from another gradient through href, as long as this
+    // one has none.
+    let mut stops_node = node;
+    for _ in 0..gradients.len() {
+        if stops_node.children().any(|child| is_svg(child, "stop")) {
+            break;
+        }
+        let href = stops_node
+            .attribute("href")
+            .or_else(|| stops_node.attribute(("http://www.w3.org/1999/xlink", "href")));
+        match href.and_then(|h| gradients.get(h.strip_prefix('#')?)) {
+            Some(linked) => stops_node = *linked,
+            None => break,
+        }
+    }
+    let stops = stops_node
+        .children()
+        .filter(|child| is_svg(*child, "stop"))
+        .map(|stop| {
+            let offset = stop
+                .attribute("offset")
+                .and_then(|v| fraction(v, "offset"))
+                .unwrap_or(0.0);
+            let mut color = property(stop, "stop-color")
+                .and_then(|v| {
+                    let color = parse_svg_color(v);
+                    if color.is_none() {
+                        log::warn!("Ignoring unsupported stop-color {:?}", v);
+                    }
+                    color
+                })
+                .unwrap_or([0.0, 0.0, 0.0, 1.0]);
+            if let Some(opacity) =
+                property(stop, "stop-opacity").and_then(|v| number(v, "stop-opacity"))
+            {
+                color[3] *= opacity.clamp(0.0, 1.0);
+            }
+            GradientStop { offset, color }
+        })
+        .collect();
+
+    let spread = match node.attribute("spreadMethod") {
+        Some("reflect") => Spread::Reflect,
+        Some("repeat") => Spread::Repeat,
+        _ => Spread::Pad,
+    };
+    let transform = node
+        .attribute("gradientTransform")
+        .map_or(Transform::identity(), parse_transform);
+    // Lengths can be percentages, which are only right for bounding box
+    // units, the default.
+    let coordinate = |name: &str, default: f32| {
+        node.attribute(name)
+            .and_then(|v| fraction(v, name))
+            .unwrap_or(default)
+    };
+    let paint = if node.tag_name().name() == "linearGradient" {
+        Paint::LinearGradient(LinearGradient {
+            start: point(coordinate("x1", 0.0), coordinate("y1", 0.0)),
+            end: point(coordinate("x2", 1.0), coordinate("y2", 0.0)),
+            stops,
+            spread,
+            transform,
+        })
+    } else {
+        let center = point(coordinate("cx", 0.5), coordinate("cy", 0.5));
+        Paint::RadialGradient(RadialGradient {
+            center,
+            radius: coordinate("r", 0.5),
+            focal: point(coordinate("fx", center.x), coordinate("fy", center.y)),
+            stops,
+            spread,
+            transform,
+        })
+    };
+    match node.attribute("gradientUnits") {
+        Some("userSpaceOnUse") => StylePaint::Paint(paint),
+        _ => StylePaint::BoundingBox(paint),
+    }
+}
+
+// A CSS color as SVG allows: "#rgb" and "#rrggbb" with their alpha forms,
+// "rgb()" and "rgba()" with numbers or percentages, and the basic color
+// keywords.
+fn parse_svg_color(value: &str) -> Option<[f32; 4]> {
+    let value = value.trim();
+    if value.starts_with('#') {
+        return parse_color(value);
+    }
+    if let Some(arguments) = value
+        .strip_prefix("rgba(")
+        .or_else(|| value.strip_prefix("rgb("))
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        let channels: Vec<&str> = arguments
+            .split(|c: char| c == ',' || c == '/' || is_whitespace(c))
+            .filter(|s| !s.is_empty())
+            .collect();
+        if !(3..=4).contains(&channels.len()) {
+            return None;
+        }
+        let mut color = [0.0, 0.0, 0.0, 1.0];
+        for (i, channel) in channels.iter().enumerate() {
+            // Color channels go to 255, alpha to 1.
+            let max = if i < 3 { 255.0 } else { 1.0 };
+            let v = match channel.strip_suffix('%') {
+                // In f64, so that percentages written by the exporter give
+                // back the exact color.
+                Some(percent) => (percent.parse::<f64>().ok()? / 100.0) as f32,
+                None => channel.parse::<f32>().ok()? / max,
+            };
+            color[i] = v.clamp(0.0, 1.0);
+        }
+        return Some(color);
+    }
+    let rgb = match value.to_ascii_lowercase().as_str() {
+        "transparent" => return Some([0.0; 4]),
+        "black" => 0x000000,
+        "silver" => 0xc0c0c0,
+        "gray" | "grey" => 0x808080,
+        "white" => 0xffffff,
+        "maroon" => 0x800000,
+        "red" => 0xff0000,
+        "purple" => 0x800080,
+        "fuchsia" | "magenta" => 0xff00ff,
+        "green" => 0x008000,
+        "lime" => 0x00ff00,
+        "olive" => 0x808000,
+        "yellow" => 0xffff00,
+        "navy" => 0x000080,
+        "blue" => 0x0000ff,
+        "teal" => 0x008080,
+        "aqua" | "cyan" => 0x00ffff,
+        "orange" => 0xffa500,
+        _ => return None,
+    };
+    let channel = |shift: u32| ((rgb >> shift) & 0xff) as f32 / 255.0;
+    Some([channel(16), channel(8), channel(0), 1.0])
+}
+
+// A transform list, e.g. "translate(10 20) rotate(45)". Stops at the first
+// invalid transform, keeping those before it.
+fn parse_transform(value: &str) -> Transform {
+    let mut transform = Transform::identity();
+    let mut rest = value.trim_start_matches(|c| is_whitespace(c) || c == ',');
+    while !rest.is_empty() {
+        let parsed = rest.split_once('(').and_then(|(name, rest)| {
+            let (arguments, rest) = rest.split_once(')')?;
+            Some((name.trim_matches(is_whitespace), numbers(arguments)?, rest))
+        });
+        let Some((name, arguments, remaining)) = parsed else {
+            log::warn!("Ignoring invalid transform {:?}", rest);
+            break;
+        };
+        let next = match (name, arguments.as_slice()) {
+            ("matrix", &[a, b, c, d, e, f]) => Transform::new(a, b, c, d, e, f),
+            ("translate", &[x]) => Transform::translation(x, 0.0),
+            ("translate", &[x, y]) => Transform::translation(x, y),
+            ("scale", &[s]) => Transform::scale(s, s),
+            ("scale", &[x, y]) => Transform::scale(x, y),
+            ("rotate", &[a]) => Transform::rotation(Angle::degrees(a)),
+            ("rotate", &[a, x, y]) => Transform::translation(-x, -y)
+                .then_rotate(Angle::degrees(a))
+                .then_translate(vector(x, y)),
+            ("skewX", &[a]) => Transform::new(1.0, 0.0, a.to_radians().tan(), 1.0, 0.0, 0.0),
+            ("skewY", &[a]) => Transform::new(1.0, a.to_radians().tan(), 0.0, 1.0, 0.0, 0.0),
+            _ => {
+                log::warn!("Ignoring invalid transform {:?}", rest);
+                break;
+            }
+        };
+        // Each transform applies before the ones to its left.
+        transform = next.then(&transform);
+        rest = remaining.trim_start_matches(|c| is_whitespace(c) || c == ',');
+    }
+    transform
+}
+
+// Numbers separated by whitespace or commas, or None if `value` has anything
+// else in it.
+fn numbers(value: &str) -> Option<Vec<f32>> {
+    let mut numbers = Vec::new();
+    let mut pos = 0;
+    loop {
+        let rest = &value[pos..];
+        let trimmed = rest.trim_start_matches(|c| is_whitespace(c) || c == ',');
+        pos += rest.len() - trimmed.len();
+        if pos == value.len() {
+            return Some(numbers);
+        }
+        let end = scan_number(value, pos)?;
+        numbers.push(
+            value[pos..end]
+                .parse::<f32>()
+                .ok()
+                .filter(|v| v.is_finite())?,
+        );
+        pos = end;
+    }
+}
+
+// A number, or a percentage of 1.
+fn fraction(value: &str, name: &str) -> Option<f32> {
+    match value.trim().strip_suffix('%') {
+        Some(percent) => number(percent, name).map(|v| v / 100.0),
+        None => number(value, name),
+    }
+}
+
+fn number(value: &str, name: &str) -> Option<f32> {
+    match numbers(value).as_deref() {
+        Some(&[v]) => Some(v),
+        _ => {
+            log::warn!("Ignoring invalid {} {:?}", name, value);
+            None
+        }
+    }
+}
+
+// A length in user units. Only "px", which is the same, is supported.
+fn length(value: &str, name: &str) -> Option<f32> {
+    number(value.strip_suffix("px").unwrap_or(value), name)
+}
+
+fn attribute_length(node: Node, name: &str) -> Option<f32> {
+    node.attribute(name).and_then(|v| length(v, name))
+}
+
+fn rect(node: Node) -> Option<Path> {
+    let x = attribute_length(node, "x").unwrap_or(0.0);
+    let y = attribute_length(node, "y").unwrap_or(0.0);
+    let width = attribute_length(node, "width").unwrap_or(0.0);
+    let height = attribute_length(node, "height").unwrap_or(0.0);
+    if width <= 0.0 || height <= 0.0 {
+        return None;
+    }
+    // A missing radius is the same as the other one.
+    let rx_attribute = attribute_length(node, "rx").filter(|r| *r >= 0.0);
+    let ry_attribute = attribute_length(node, "ry").filter(|r| *r >= 0.0);
+    let rx = rx_attribute
+        .or(ry_attribute)
+        .unwrap_or(0.0)
+        .min(width / 2.0);
+    let ry = ry_attribute
+        .or(rx_attribute)
+        .unwrap_or(0.0)
+        .min(height / 2.0);
+
+    let mut builder = Path::svg_builder();
+    if rx == 0.0 || ry == 0.0 {
+        builder.move_to(point(x, y));
+        builder.horizontal_line_to(x + width);
+        builder.vertical_line_to(y + height);
+        builder.horizontal_line_to(x);
+        builder.close();
+        return Some(builder.build());
+    }
+    // As SVG draws it, clockwise from the end of the top left corner.
+    let radii = vector(rx, ry);
+    let flags = ArcFlags {
+        large_arc: false,
+        sweep: true,
+    };
+    let no_rotation = Angle::radians(0.0);
+    builder.move_to(point(x + rx, y));
+    builder.horizontal_line_to(x + width - rx);
+    builder.arc_to(radii, no_rotation, flags, point(x + width, y + ry));
+    builder.vertical_line_to(y + height - ry);
+    builder.arc_to(radii, no_rotation, flags, point(x + width - rx, y + height));
+    builder.horizontal_line_to(x + rx);
+    builder.arc_to(radii, no_rotation, flags, point(x, y + height - ry));
+    builder.vertical_line_to(y + ry);
+    builder.arc_to(radii, no_rotation, flags, point(x + rx, y));
+    builder.close();
+    Some(builder.build())
+}
+
+fn circle(node: Node) -> Option<Path> {
+    let cx = attribute_length(node, "cx").unwrap_or(0.0);
+    let cy = attribute_length(node, "cy").unwrap_or(0.0);
+    let r = attribute_length(node, "r").filter(|r| *r > 0.0)?;
+    let mut builder = Path::builder();
+    builder.add_circle(point(cx, cy), r, Winding::Positive);
+    Some(builder.build())
+}
+
+fn ellipse(node: Node) -> Option<Path> {
+    let cx = attribute_length(node, "cx").unwrap_or(0.0);
+    let cy = attribute_length(node, "cy").unwrap_or(0.0);
+    let rx = attribute_length(node, "rx").filter(|r| *r > 0.0)?;
+    let ry = attribute_length(node, "ry").filter(|r| *r > 0.0)?;
+    let mut builder = Path::builder();
+    builder.add_ellipse(
+        point(cx, cy),
+        vector(rx, ry),
+        Angle::radians(0.0),
+        Winding::Positive,
+    );
+    Some(builder.build())
+}
+
+fn line(node: Node) -> Option<Path> {
+    let coordinate = |name| attribute_length(node, name).unwrap_or(0.0);
+    let mut builder = Path::builder();
+    builder.begin(point(coordinate("x1"), coordinate("y1")));
+    builder.line_to(point(coordinate("x2"), coordinate("y2")));
+    builder.end(false);
+    Some(builder.build())
+}
+
+fn poly(node: Node, close: bool) -> Option<Path> {
+    let value = node.attribute("points").unwrap_or("");
+    let Some(mut coordinates) = numbers(value) else {
+        log::warn!("Skipping <{}> with invalid points", node.tag_name().name());
+        return None;
+    };
+    // Like browsers, draw up to a missing coordinate.
+    if coordinates.len() % 2 != 0 {
+        log::warn!("Odd number of coordinates in points {:?}", value);
+        coordinates.pop();
+    }
+    let mut points = coordinates.chunks_exact(2).map(|c| point(c[0], c[1]));
+    let mut builder = Path::builder();
+    builder.begin(points.next()?);
+    for p in points {
+        builder.line_to(p);
+    }
+    builder.end(close);
+    Some(builder.build())
+}
@@ -0,0 +1,287 @@
+//! The path data mini-language of the `d` attribute.
+
+use std::fmt;
+
+use lyon::geom::{Angle, ArcFlags};
+use lyon::math::{point, vector, Point};
+use lyon::path::builder::{SvgPathBuilder, WithSvg};
+use lyon::path::path::BuilderImpl;
+use lyon::path::Path;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathError {
+    /// Byte offset in the path data of where parsing stopped.
+    pub offset: usize,
+    pub kind: PathErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PathErrorKind {
+    /// Path data has to start with `M` or `m`.
+    ExpectedMoveTo,
+    /// Neither a command nor the start of a number, or a number where the
+    /// previous command can't repeat.
+    UnexpectedCharacter(char),
+    ExpectedNumber,
+    /// Too large to fit an `f32`.
+    NumberOutOfRange,
+    /// Arc flags are a single `0` or `1`.
+    ExpectedFlag,
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            PathErrorKind::ExpectedMoveTo => write!(f, "expected a move to")?,
+            PathErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected {:?}", c)?,
+            PathErrorKind::ExpectedNumber => write!(f, "expected a number")?,
+            PathErrorKind::NumberOutOfRange => write!(f, "number out of range")?,
+            PathErrorKind::ExpectedFlag => write!(f, "expected a flag, 0 or 1")?,
+        }
+        write!(f, " at byte {}", self.offset)
+    }
+}
+
+impl std::error::Error for PathError {}
+
+/// Parses SVG path data, e.g. `"M10 10 h 20 a5 5 0 0 1 -5 5 z"`, into a path.
+/// All commands are supported, absolute and relative. Arcs are approximated
+/// with quadratic curves.
+pub fn parse_path(d: &str) -> Result<Path, PathError> {
+    PathParser {
+        d,
+        pos: 0,
+        builder: Path::svg_builder(),
+        previous: Previous::Other,
+    }
+    .parse()
+}
+
+// What the control point of a following smooth curve is reflected from.
+#[derive(Copy, Clone)]
+enum Previous {
+    Cubic(Point),
+    Quadratic(Point),
+    Other,
+}
+
+struct PathParser<'a> {
+    d: &'a str,
+    pos: usize,
+    builder: WithSvg<BuilderImpl>,
+    previous: Previous,
+}
+
+impl PathParser<'_> {
+    fn parse(mut self) -> Result<Path, PathError> {
+        // The command repeated when more arguments follow. Moves repeat as
+        // lines.
+        let mut command = None;
+        loop {
+            self.skip_whitespace();
+            let Some(c) = self.peek() else {
+                break;
+            };
+            let start = self.pos;
+            if c.is_ascii_alphabetic() {
+                self.pos += 1;
+                if command.is_none() && !matches!(c, 'M' | 'm') {
+                    return Err(self.error_at(start, PathErrorKind::ExpectedMoveTo));
+                }
+                command = Some(c);
+            } else if !(starts_number(c) && command.is_some_and(|c| !matches!(c, 'Z' | 'z'))) {
+                let kind = if command.is_none() {
+                    PathErrorKind::ExpectedMoveTo
+                } else {
+                    PathErrorKind::UnexpectedCharacter(c)
+                };
+                return Err(self.error_at(start, kind));
+            }
+            let c = command.unwrap();
+            self.command(c, start)?;
+            command = match c {
+                'M' => Some('L'),
+                'm' => Some('l'),
+                _ => Some(c),
+            };
+        }
+        Ok(self.builder.build())
+    }
+
+    // Parses the arguments of one `command` starting at `start` and adds it
+    // to the path.
+    fn command(&mut self, command: char, start: usize) -> Result<(), PathError> {
+        let current = self.builder.current_position();
+        let relative = command.is_ascii_lowercase();
+        let origin = if relative { current } else { point(0.0, 0.0) };
+        let mut previous = Previous::Other;
+        match command.to_ascii_uppercase() {
+            'M' => {
+                let to = origin + self.pair()?;
+                self.builder.move_to(to);
+            }
+            'L' => {
+                let to = origin + self.pair()?;
+                self.builder.line_to(to);
+            }
+            'H' => {
+                let x = origin.x + self.number()?;
+                self.builder.line_to(point(x, current.y));
+            }
+            'V' => {
+                let y = origin.y + self.number()?;
+                self.builder.line_to(point(current.x, y));
+            }
+            'C' => {
+                let ctrl1 = origin + self.pair()?;
+                let ctrl2 = origin + self.pair()?;
+                let to = origin + self.pair()?;
+                self.builder.cubic_bezier_to(ctrl1, ctrl2, to);
+                previous = Previous::Cubic(ctrl2);
+            }
+            'S' => {
+                let ctrl1 = match self.previous {
+                    Previous::Cubic(ctrl) => current + (current - ctrl),
+                    _ => current,
+                };
+                let ctrl2 = origin + self.pair()?;
+                let to = origin + self.pair()?;
+                self.builder.cubic_bezier_to(ctrl1, ctrl2, to);
+                previous = Previous::Cubic(ctrl2);
+            }
+            'Q' => {
+                let ctrl = origin + self.pair()?;
+                let to = origin + self.pair()?;
+                self.builder.quadratic_bezier_to(ctrl, to);
+                previous = Previous::Quadratic(ctrl);
+            }
+            'T' => {
+                let ctrl = match self.previous {
+                    Previous::Quadratic(ctrl) => current + (current - ctrl),
+                    _ => current,
+                };
+                let to = origin + self.pair()?;
+                self.builder.quadratic_bezier_to(ctrl, to);
+                previous = Previous::Quadratic(ctrl);
+            }
+            'A' => {
+                // Negative radii are used as positive ones.
+                let radii = vector(self.number()?.abs(), self.number()?.abs());
+                let x_rotation = Angle::degrees(self.number()?);
+                let large_arc = self.flag()?;
+                let sweep = self.flag()?;
+                let to = origin + self.pair()?;
+                let flags = ArcFlags { large_arc, sweep };
+                self.builder.arc_to(radii, x_rotation, flags, to);
+            }
+            'Z' => self.builder.close(),
+            _ => {
+                return Err(self.error_at(start, PathErrorKind::UnexpectedCharacter(command)));
+            }
+        }
+        self.previous = previous;
+        Ok(())
+    }
+
+    fn pair(&mut self) -> Result<lyon::math::Vector, PathError> {
+        Ok(vector(self.number()?, self.number()?))
+    }
+
+    // A number and the separator after it.
+    fn number(&mut self) -> Result<f32, PathError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let Some(end) = scan_number(self.d, start) else {
+            return Err(self.error_at(start, PathErrorKind::ExpectedNumber));
+        };
+        let value: f32 = self.d[start..end].parse().unwrap();
+        if !value.is_finite() {
+            return Err(self.error_at(start, PathErrorKind::NumberOutOfRange));
+        }
+        self.pos = end;
+        self.skip_separator();
+        Ok(value)
+    }
+
+    // Flags can be written without separators, as in "a1 1 0 00 1 1".
+    fn flag(&mut self) -> Result<bool, PathError> {
+        self.skip_whitespace();
+        let flag = match self.peek() {
+            Some('0') => false,
+            Some('1') => true,
+            _ => return Err(self.error_at(self.pos, PathErrorKind::ExpectedFlag)),
+        };
+        self.pos += 1;
+        self.skip_separator();
+        Ok(flag)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.d[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.d[self.pos..];
+        self.pos += rest.len() - rest.trim_start_matches(is_whitespace).len();
+    }
+
+    // Whitespace with at most one comma in it.
+    fn skip_separator(&mut self) {
+        self.skip_whitespace();
+        if self.peek() == Some(',') {
+            self.pos += 1;
+            self.skip_whitespace();
+        }
+    }
+
+    fn error_at(&self, offset: usize, kind: PathErrorKind) -> PathError {
+        PathError { offset, kind }
+    }
+}
+
+/// The end of the number starting at byte `start` of `s`, if one does.
+/// Numbers are as in SVG: an optional sign, digits with an optional decimal
+/// point, and an optional exponent.
+pub(super) fn scan_number(s: &str, start: usize) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let digits = |pos: &mut usize| {
+        let from = *pos;
+        while bytes.get(*pos).is_some_and(u8::is_ascii_digit) {
+            *pos += 1;
+        }
+        *pos > from
+    };
+
+    let mut end = start;
+    if matches!(bytes.get(end), Some(b'+' | b'-')) {
+        end += 1;
+    }
+    let mut mantissa = digits(&mut end);
+    if bytes.get(end) == Some(&b'.') {
+        end += 1;
+        mantissa |= digits(&mut end);
+    }
+    if !mantissa {
+        return None;
+    }
+    // Only an exponent if digits follow, so "2em" isn't swallowed.
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let mut exponent = end + 1;
+        if matches!(bytes.get(exponent), Some(b'+' | b'-')) {
+            exponent += 1;
+        }
+        if digits(&mut exponent) {
+            end = exponent;
+        }
+    }
+    Some(end)
+}
+
+// As defined by SVG, which is narrower than char::is_whitespace.
+pub(super) fn is_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0C')
+}
+
+fn starts_number(c: char) -> bool {
+    c.is_ascii_digit() || matches!(c, '+' | '-' | '.')
+}
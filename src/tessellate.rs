@@ -0,0 +1,739 @@
+//! Turns lyon paths into triangle geometry the render pipeline can draw.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use lyon::geom::{CubicBezierSegment, QuadraticBezierSegment};
+use lyon::math::{point, Box2D, Point, Transform, Vector};
+use lyon::path::{Event as PathEvent, Path, Polygon, Side, Winding};
+use lyon::tessellation::{
+    BuffersBuilder, FillOptions, FillTessellator, FillVertex, LineJoin, StrokeOptions,
+    StrokeTessellator, StrokeVertex, TessellationError, VertexBuffers,
+};
+
+use crate::batch::Batcher;
+use crate::paint::PaintUniform;
+use crate::scene::{GroupId, ImageId, Scene, ShapeId};
+use crate::shapes::Primitive;
+use crate::{InstanceRaw, Vertex};
+
+/// Indices are 32 bits so any number of shapes fits in one buffer. The
+/// renderer uploads them as 16 bits when there are few enough vertices.
+pub type Geometry = VertexBuffers<Vertex, u32>;
+
+/// How far edges are pushed out by when feathering, in device pixels. The
+/// fade spans twice that, centered on the edge.
+pub const FEATHER: f32 = 0.5;
+
+/// The stencil buffer holds 8 bits per pixel, counting the clips a pixel is
+/// inside of.
+pub const MAX_CLIP_DEPTH: usize = u8::MAX as usize;
+
+/// What a draw does with the stencil buffer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DrawKind {
+    /// Paints the pixels whose stencil value is the draw's.
+    Paint,
+    /// Adds a clip, raising the stencil value of the pixels it covers that
+    /// have the draw's. Nothing is painted.
+    PushClip,
+    /// Takes a clip out again, lowering the stencil value of the pixels it
+    /// covers that have the draw's.
+    PopClip,
+}
+
+/// A range of indices drawn with one paint.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Draw {
+    pub indices: Range<u32>,
+    /// Index in [`Tessellation::paints`].
+    pub paint: usize,
+    /// The image the paint samples, and whether it repeats.
+    pub image: Option<(ImageId, bool)>,
+    pub kind: DrawKind,
+    /// The stencil value of the pixels drawn to, the number of clips they
+    /// are inside of.
+    pub stencil: u32,
+    /// Copies drawn, as indices in [`Tessellation::instances`].
+    pub instances: Range<u32>,
+}
+
+/// What some vertices were tessellated for.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Source {
+    Shape(ShapeId),
+    /// The clip of a group, with the number of times it was drawn before, as
+    /// it is drawn again after every shape outside it.
+    Clip(GroupId, u32),
+}
+
+/// The vertices of one shape or clip.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    pub source: Source,
+    pub vertices: Range<u32>,
+}
+
+/// Everything the renderer uploads to draw a scene at one zoom level.
+pub struct Tessellation {
+    pub geometry: Geometry,
+    /// In drawing order, once [`Tessellation::finish`] has batched them.
+    /// Clips are popped by drawing the indices that pushed them a second
+    /// time.
+    pub draws: Vec<Draw>,
+    /// The first one is for solid colors, shared by every shape using one.
+    /// Shapes with the same gradient or image share one too.
+    pub paints: Vec<PaintUniform>,
+    /// The first one is the identity, for geometry drawn once.
+    pub instances: Vec<InstanceRaw>,
+    /// In the order of the vertices, each source once, so the renderer can
+    /// tell which vertices belong to what.
+    pub spans: Vec<Span>,
+    // The index in `paints` of each uniform, by its bytes.
+    paint_indices: HashMap<Vec<u8>, usize>,
+    batcher: Batcher,
+    // How far, in scene units, the shader may move a vertex along a normal
+    // of length one.
+    bleed: f32,
+}
+
+impl Tessellation {
+    /// `bleed` is how far, in scene units, the shader moves vertices along
+    /// their normals, zero unless feathering.
+    pub fn new(bleed: f32) -> Self {
+        let solid = PaintUniform::solid();
+        Self {
+            geometry: Geometry::new(),
+            draws: Vec::new(),
+            paints: vec![solid],
+            instances: vec![InstanceRaw::IDENTITY],
+            spans: Vec::new(),
+            paint_indices: HashMap::from([(bytemuck::bytes_of(&solid).to_vec(), 0)]),
+            batcher: Batcher::new(),
+            bleed,
+        }
+    }
+
+    /// The index of `uniform` in [`Tessellation::paints`], adding it unless
+    /// an identical one is already there.
+    pub fn add_paint(&mut self, uniform: PaintUniform) -> usize {
+        let paints = &mut self.paints;
+        *self
+            .paint_indices
+            .entry(bytemuck::bytes_of(&uniform).to_vec())
+            .or_insert_with(|| {
+                paints.push(uniform);
+                paints.len() - 1
+            })
+    }
+
+    /// Adds the vertices tessellated for `source`, returning the index of
+    /// the first.
+    pub fn add_vertices(&mut self, source: Source, vertices: &[Vertex]) -> u32 {
+        let start = self.geometry.vertices.len() as u32;
+        self.geometry.vertices.extend_from_slice(vertices);
+        let end = self.geometry.vertices.len() as u32;
+        if start < end {
+            self.spans.push(Span {
+                source,
+                vertices: start..end,
+            });
+        }
+        start
+    }
+
+    /// Adds `indices`, counted from vertex `first_vertex`, returning how many
+    /// indices there were before.
+    pub fn add_indices(&mut self, indices: &[u32], first_vertex: u32) -> u32 {
+        let start = self.geometry.indices.len() as u32;
+        self.geometry
+            .indices
+            .extend(indices.iter().map(|i| i + first_vertex));
+        start
+    }
+
+    /// Draws the indices added since there were `start` of them with
+    /// `paint` and `image`, inside `depth` clips, once for each of
+    /// `instances`. Joins an earlier draw using the same when that doesn't
+    /// change the picture.
+    pub fn push_draw(
+        &mut self,
+        start: u32,
+        paint: usize,
+        image: Option<(ImageId, bool)>,
+        depth: u32,
+        instances: Range<u32>,
+    ) {
+        let end = self.geometry.indices.len() as u32;
+        if start == end {
+            return;
+        }
+        let bounds = self.bounds(start..end, instances.clone());
+        self.batcher.push(
+            Draw {
+                indices: start..end,
+                paint,
+                image,
+                kind: DrawKind::Paint,
+                stencil: depth,
+                instances,
+            },
+            bounds,
+        );
+    }
+
+    /// Adds the clip filled by `indices` on top of `depth` others.
+    pub fn push_clip(&mut self, indices: Range<u32>, depth: u32) {
+        self.push_stencil(indices, DrawKind::PushClip, depth);
+    }
+
+    /// Takes out the clip [`Tessellation::push_clip`] added with the same
+    /// arguments.
+    pub fn pop_clip(&mut self, indices: Range<u32>, depth: u32) {
+        self.push_stencil(indices, DrawKind::PopClip, depth + 1);
+    }
+
+    fn push_stencil(&mut self, indices: Range<u32>, kind: DrawKind, stencil: u32) {
+        if indices.is_empty() {
+            return;
+        }
+        let draw = Draw {
+            indices,
+            paint: 0,
+            image: None,
+            kind,
+            stencil,
+            instances: 0..1,
+        };
+        self.batcher.push(draw, Box2D::zero());
+    }
+
+    /// Fills in [`Tessellation::draws`], ordering the indices so that draws
+    /// sharing a paint are one draw call as often as possible.
+    pub fn finish(&mut self) {
+        let batcher = std::mem::replace(&mut self.batcher, Batcher::new());
+        let (indices, draws) = batcher.finish(&self.geometry.indices);
+        self.geometry.indices = indices;
+        self.draws = draws;
+    }
+
+    // The scene-space area `indices` may paint once placed by each of
+    // `instances`.
+    fn bounds(&self, indices: Range<u32>, instances: Range<u32>) -> Box2D {
+        let mut local = Box2D::new(
+            point(f32::INFINITY, f32::INFINITY),
+            point(f32::NEG_INFINITY, f32::NEG_INFINITY),
+        );
+        let mut reach: f32 = 0.0;
+        for &i in &self.geometry.indices[indices.start as usize..indices.end as usize] {
+            let vertex = &self.geometry.vertices[i as usize];
+            let p = point(vertex.position[0], vertex.position[1]);
+            local.min = local.min.min(p);
+            local.max = local.max.max(p);
+            let [x, y] = vertex.normal;
+            reach = reach.max((x * x + y * y).sqrt() * vertex.side.abs());
+        }
+        let mut bounds = local;
+        if instances != (0..1) {
+            let corners = [
+                local.min,
+                point(local.max.x, local.min.y),
+                local.max,
+                point(local.min.x, local.max.y),
+            ];
+            let placed = self.instances[instances.start as usize..instances.end as usize]
+                .iter()
+                .flat_map(|instance| {
+                    let [x, y, translation] = instance.transform;
+                    corners.iter().map(move |c| {
+                        point(
+                            x[0] * c.x + y[0] * c.y + translation[0],
+                            x[1] * c.x + y[1] * c.y + translation[1],
+                        )
+                    })
+                });
+            bounds = Box2D::from_points(placed);
+        }
+        bounds.inflate(reach * self.bleed, reach * self.bleed)
+    }
+}
+
+/// How far, in device pixels, flattened curves may stray from the real ones.
+pub const DEVICE_TOLERANCE: f32 = 0.25;
+
+/// The most `transform` stretches lengths by, in any direction: the largest
+/// singular value of its linear part. Tolerances divided by it hold along
+/// every axis, even under a non-uniform scale or a skew.
+pub fn scale_factor(transform: &Transform) -> f32 {
+    let (a, b, c, d) = (transform.m11, transform.m12, transform.m21, transform.m22);
+    let q = ((a + d) * 0.5).hypot((b - c) * 0.5);
+    let r = ((a - d) * 0.5).hypot((b + c) * 0.5);
+    q + r
+}
+
+/// Path-space tolerance that keeps curves within [`DEVICE_TOLERANCE`] once
+/// drawn at `pixel_scale` device pixels per path unit.
+pub fn tolerance_for_scale(pixel_scale: f32) -> f32 {
+    DEVICE_TOLERANCE / pixel_scale.max(f32::EPSILON)
+}
+
+/// The power-of-two bucket a camera zoom falls in. The scene is tessellated
+/// for the largest zoom of its bucket, so curves stay within
+/// [`DEVICE_TOLERANCE`] anywhere in it.
+pub fn zoom_level(zoom: f32) -> i32 {
+    zoom.max(f32::MIN_POSITIVE).log2().ceil() as i32
+}
+
+// Going back and forth between a few zoom levels is common, going through
+// many of them rarely is.
+const CACHED_LEVELS: usize = 4;
+
+/// Remembers the scene's tessellation at the zoom levels used most recently,
+/// so zooming back doesn't tessellate again. Any change to the scene, to the
+/// display scale factor or to feathering empties it; the [`Tessellator`]
+/// still has the shapes that didn't change.
+pub struct TessellationCache {
+    scene_version: u64,
+    scale_factor: f32,
+    feathering: bool,
+    // Least recently used first.
+    levels: Vec<(i32, Tessellation)>,
+}
+
+impl TessellationCache {
+    pub fn new() -> Self {
+        Self {
+            scene_version: 0,
+            scale_factor: 0.0,
+            feathering: false,
+            levels: Vec::new(),
+        }
+    }
+
+    pub fn get(
+        &mut self,
+        scene: &Scene,
+        tessellator: &mut Tessellator,
+        scale_factor: f32,
+        level: i32,
+        feathering: bool,
+    ) -> &Tessellation {
+        if scene.version() != self.scene_version
+            || scale_factor != self.scale_factor
+            || feathering != self.feathering
+        {
+            self.levels.clear();
+            self.scene_version = scene.version();
+            self.scale_factor = scale_factor;
+            self.feathering = feathering;
+        }
+
+        let entry = match self.levels.iter().position(|(l, _)| *l == level) {
+            Some(index) => self.levels.remove(index),
+            None => {
+                log::debug!("Tessellating the scene for zoom level {}", level);
+                let pixel_scale = scale_factor * 2f32.powi(level);
+                // Zooming out within the level makes pixels up to twice as
+                // large as those tessellated for.
+                let bleed = if feathering {
+                    2.0 * FEATHER / pixel_scale
+                } else {
+                    0.0
+                };
+                let mut tessellation = Tessellation::new(bleed);
+                scene.tessellate(tessellator, pixel_scale, feathering, &mut tessellation);
+                if self.levels.len() == CACHED_LEVELS {
+                    self.levels.remove(0);
+                }
+                (level, tessellation)
+            }
+        };
+        self.levels.push(entry);
+        &self.levels.last().unwrap().1
+    }
+}
+
+/// Replaces every quadratic and cubic Bézier segment of `path` with line
+/// segments that stay within `tolerance` of the curve.
+pub fn flatten(path: &Path, tolerance: f32) -> Path {
+    let mut builder = Path::builder();
+    for event in path.iter() {
+        match event {
+            PathEvent::Begin { at } => {
+                builder.begin(at);
+            }
+            PathEvent::Line { to, .. } => {
+                builder.line_to(to);
+            }
+            PathEvent::Quadratic { from, ctrl, to } => {
+                let curve = QuadraticBezierSegment { from, ctrl, to };
+                for p in curve.flattened(tolerance) {
+                    builder.line_to(p);
+                }
+            }
+            PathEvent::Cubic {
+                from,
+                ctrl1,
+                ctrl2,
+                to,
+            } => {
+                let curve = CubicBezierSegment {
+                    from,
+                    ctrl1,
+                    ctrl2,
+                    to,
+                };
+                for p in curve.flattened(tolerance) {
+                    builder.line_to(p);
+                }
+            }
+            PathEvent::End { close, .. } => {
+                builder.end(close);
+            }
+        }
+    }
+    builder.build()
+}
+
+/// The geometry of one shape or clip, with indices counted from its first
+/// vertex.
+#[derive(Default)]
+pub struct Piece {
+    pub vertices: Vec<Vertex>,
+    /// For clips, all of them.
+    pub fill: Vec<u32>,
+    pub stroke: Vec<u32>,
+}
+
+/// Everything the geometry of a shape or clip depends on. The same key
+/// gives the same [`Piece`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PieceKey {
+    /// Of the shape, or of the group for a clip.
+    pub revision: u64,
+    pub pixel_scale: f32,
+    pub feathering: bool,
+    /// Multiplied into the vertex colors.
+    pub opacity: f32,
+}
+
+/// Owns the lyon tessellators so their internal allocations are reused
+/// between paths, and the pieces of the last scene tessellated, so shapes
+/// that didn't change since aren't tessellated again.
+pub struct Tessellator {
+    fill: FillTessellator,
+    stroke: StrokeTessellator,
+    // Pieces of the scene being tessellated, and of the one before.
+    pieces: HashMap<Source, (PieceKey, Piece)>,
+    previous: HashMap<Source, (PieceKey, Piece)>,
+}
+
+impl Tessellator {
+    pub fn new() -> Self {
+        Self {
+            fill: FillTessellator::new(),
+            stroke: StrokeTessellator::new(),
+            pieces: HashMap::new(),
+            previous: HashMap::new(),
+        }
+    }
+
+    /// Starts tessellating a scene. Pieces kept before can be reused until
+    /// the next call, then those not reused are dropped.
+    pub fn start(&mut self) {
+        self.previous = std::mem::take(&mut self.pieces);
+    }
+
+    /// The piece kept for `source` by the last scene, if it was made from
+    /// `key`.
+    pub fn reuse(&mut self, source: Source, key: &PieceKey) -> Option<Piece> {
+        match self.previous.remove(&source) {
+            Some((k, piece)) if k == *key => Some(piece),
+            _ => None,
+        }
+    }
+
+    /// Keeps `piece` for the next scene to reuse.
+    pub fn keep(&mut self, source: Source, key: PieceKey, piece: Piece) {
+        self.pieces.insert(source, (key, piece));
+    }
+
+    /// Fills the inside of `path` and appends the triangles to `geometry`.
+    /// `color` is premultiplied and multiplies the paint the triangles are
+    /// drawn with. When there is a `primitive`, it is tessellated instead of
+    /// the path.
+    pub fn fill(
+        &mut self,
+        path: &Path,
+        primitive: Option<&Primitive>,
+        options: &FillOptions,
+        color: [f32; 4],
+        transform: &Transform,
+        geometry: &mut Geometry,
+    ) -> Result<(), TessellationError> {
+        let output = &mut BuffersBuilder::new(geometry, |vertex: FillVertex| {
+            let p = transform.transform_point(vertex.position());
+            Vertex {
+                position: [p.x, p.y, 0.0],
+                local: vertex.position().to_array(),
+                color,
+                normal: [0.0; 2],
+                side: 0.0,
+                half_width: 0.0,
+            }
+        });
+        match primitive {
+            Some(Primitive::Rect(rect)) => self.fill.tessellate_rectangle(rect, options, output),
+            Some(Primitive::Circle { center, radius }) => self
+                .fill
+                .tessellate_circle(*center, *radius, options, output),
+            Some(Primitive::Ellipse {
+                center,
+                radii,
+                rotation,
+            }) => self.fill.tessellate_ellipse(
+                *center,
+                *radii,
+                *rotation,
+                Winding::Positive,
+                options,
+                output,
+            ),
+            Some(Primitive::Polygon(points)) => {
+                self.fill
+                    .tessellate_polygon(polygon(points), options, output)
+            }
+            None => self.fill.tessellate_path(path, options, output),
+        }
+    }
+
+    /// Adds a strip just outside the outline of `path` that the shader fades
+    /// out when feathering is on, smoothing the edges of a fill tessellated
+    /// with the same `tolerance`. Subpaths are closed, as filling does.
+    ///
+    /// Which side of a subpath is outside is told from its winding. That is
+    /// wrong for holes winding the same way as the outline around them,
+    /// whose edges then stay aliased.
+    pub fn feather(
+        &mut self,
+        path: &Path,
+        tolerance: f32,
+        color: [f32; 4],
+        transform: &Transform,
+        geometry: &mut Geometry,
+    ) -> Result<(), TessellationError> {
+        // The strip is built on the outline itself and widened in the vertex
+        // shader, so the width only matters to how lyon handles sharp
+        // corners. One device pixel keeps it close to the final one. Round
+        // joins would need far more vertices for no visible gain.
+        let options = StrokeOptions::tolerance(tolerance)
+            .with_line_width(tolerance / DEVICE_TOLERANCE)
+            .with_line_join(LineJoin::Miter);
+        let scale = scale_factor(transform);
+        let mut subpath = Vec::new();
+        for event in path.iter() {
+            let (last, first) = match event {
+                PathEvent::End { last, first, .. } => (last, first),
+                event => {
+                    subpath.push(event);
+                    continue;
+                }
+            };
+            subpath.push(PathEvent::End {
+                last,
+                first,
+                close: true,
+            });
+            // With y pointing down, positive areas wind clockwise on screen
+            // and have the inside on their positive side.
+            let outside = if signed_area(&subpath, tolerance) > 0.0 {
+                Side::Negative
+            } else {
+                Side::Positive
+            };
+            self.stroke.tessellate(
+                subpath.drain(..),
+                &options,
+                &mut BuffersBuilder::new(geometry, |vertex: StrokeVertex| {
+                    let local = vertex.position_on_path();
+                    let p = transform.transform_point(local);
+                    let normal = edge_normal(&vertex, transform, scale);
+                    // Inner vertices stay on the outline, with the normal of
+                    // the outer ones, so the strip doesn't cover the fill and
+                    // blend over it a second time.
+                    let (normal, side) = if vertex.side() == outside {
+                        (normal, 1.0)
+                    } else {
+                        ([-normal[0], -normal[1]], 0.0)
+                    };
+                    Vertex {
+                        position: [p.x, p.y, 0.0],
+                        local: local.to_array(),
+                        color,
+                        normal,
+                        side,
+                        half_width: 0.0,
+                    }
+                }),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Strokes the outline of `path`, or of `primitive` when there is one.
+    /// Line width, caps, joins, miter limit and tolerance all come from
+    /// `options`; the width is expressed in path units, before `transform` is
+    /// applied.
+    pub fn stroke(
+        &mut self,
+        path: &Path,
+        primitive: Option<&Primitive>,
+        options: &StrokeOptions,
+        color: [f32; 4],
+        transform: &Transform,
+        geometry: &mut Geometry,
+    ) -> Result<(), TessellationError> {
+        let scale = scale_factor(transform);
+        let output = &mut BuffersBuilder::new(geometry, |vertex: StrokeVertex| {
+            let p = transform.transform_point(vertex.position());
+            Vertex {
+                position: [p.x, p.y, 0.0],
+                local: vertex.position().to_array(),
+                color,
+                normal: edge_normal(&vertex, transform, scale),
+                side: side_sign(vertex.side()),
+                half_width: vertex.line_width() * 0.5 * scale,
+            }
+        });
+        match primitive {
+            Some(Primitive::Rect(rect)) => self.stroke.tessellate_rectangle(rect, options, output),
+            Some(Primitive::Circle { center, radius }) => self
+                .stroke
+                .tessellate_circle(*center, *radius, options, output),
+            Some(Primitive::Ellipse {
+                center,
+                radii,
+                rotation,
+            }) => self.stroke.tessellate_ellipse(
+                *center,
+                *radii,
+                *rotation,
+                Winding::Positive,
+                options,
+                output,
+            ),
+            Some(Primitive::Polygon(points)) => {
+                self.stroke
+                    .tessellate_polygon(polygon(points), options, output)
+            }
+            None => self.stroke.tessellate_path(path, options, output),
+        }
+    }
+}
+
+// The vertex's stroke normal in scene space, keeping the length lyon gives
+// it in path space so miters stay miters.
+fn edge_normal(vertex: &StrokeVertex, transform: &Transform, scale: f32) -> [f32; 2] {
+    let normal: Vector = transform.transform_vector(vertex.normal()) / scale.max(f32::EPSILON);
+    normal.to_array()
+}
+
+// Twice the area enclosed by a closed subpath, with its curves flattened
+// within `tolerance`: their control points alone can give the wrong sign for
+// strongly bent curves. Only the sign is needed.
+fn signed_area(events: &[PathEvent<Point, Point>], tolerance: f32) -> f32 {
+    let mut builder = Path::builder();
+    for event in events {
+        builder.path_event(*event);
+    }
+    let mut points: Vec<Point> = Vec::new();
+    for event in flatten(&builder.build(), tolerance).iter() {
+        match event {
+            PathEvent::Begin { at } => points.push(at),
+            PathEvent::Line { to, .. } => points.push(to),
+            _ => {}
+        }
+    }
+    let mut area = 0.0;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        area += a.x * b.y - b.x * a.y;
+    }
+    area
+}
+
+fn polygon(points: &[Point]) -> Polygon<'_, Point> {
+    Polygon {
+        points,
+        closed: true,
+    }
+}
+
+fn side_sign(side: Side) -> f32 {
+    match side {
+        Side::Positive => 1.0,
+        Side::Negative => -1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_factor_is_the_largest_stretch() {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-3 * b.max(1.0);
+        assert!(close(scale_factor(&Transform::identity()), 1.0));
+        assert!(close(scale_factor(&Transform::scale(3.0, 3.0)), 3.0));
+        // The average, sqrt(100 * 0.01), would be 1.
+        assert!(close(scale_factor(&Transform::scale(100.0, 0.01)), 100.0));
+        assert!(close(scale_factor(&Transform::scale(-2.0, 0.5)), 2.0));
+        let rotated = Transform::scale(4.0, 1.0).then_rotate(lyon::geom::Angle::degrees(30.0));
+        assert!(close(scale_factor(&rotated), 4.0));
+        // A shear moving (0, 1) to (1, 1): its largest singular value is the
+        // golden ratio.
+        let skew = Transform::new(1.0, 0.0, 1.0, 1.0, 0.0, 0.0);
+        assert!(close(scale_factor(&skew), (1.0 + 5f32.sqrt()) / 2.0));
+        assert!(close(scale_factor(&Transform::scale(0.0, 0.0)), 0.0));
+    }
+
+    #[test]
+    fn flattened_curves_stay_within_tolerance() {
+        let curve = CubicBezierSegment {
+            from: point(0.0, 0.0),
+            ctrl1: point(10.0, 40.0),
+            ctrl2: point(60.0, -30.0),
+            to: point(50.0, 20.0),
+        };
+        let mut builder = Path::builder();
+        builder.begin(curve.from);
+        builder.cubic_bezier_to(curve.ctrl1, curve.ctrl2, curve.to);
+        builder.end(false);
+        let tolerance = 0.1;
+        let mut points = Vec::new();
+        for event in flatten(&builder.build(), tolerance).iter() {
+            match event {
+                PathEvent::Begin { at } => points.push(at),
+                PathEvent::Line { to, .. } => points.push(to),
+                PathEvent::End { .. } => {}
+                event => panic!("not flattened: {:?}", event),
+            }
+        }
+        assert!(points.len() > 8, "{:?}", points);
+        assert_eq!(points.first(), Some(&curve.from));
+        assert_eq!(points.last(), Some(&curve.to));
+        let on_curve: Vec<Point> = (0..=4000)
+            .map(|i| curve.sample(i as f32 / 4000.0))
+            .collect();
+        for segment in points.windows(2) {
+            for i in 0..=16 {
+                let p = segment[0].lerp(segment[1], i as f32 / 16.0);
+                let distance = on_curve
+                    .iter()
+                    .map(|c| (*c - p).length())
+                    .fold(f32::INFINITY, f32::min);
+                assert!(distance <= tolerance + 0.01, "{:?} is {} away", p, distance);
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Splitting paths into dashes.
+
+use lyon::algorithms::length::approximate_length;
+use lyon::math::{point, Box2D, Point};
+use lyon::path::{Event, Path, Winding};
+
+use wgpu_lyon::dash::{dash, DashPattern};
+
+const TOLERANCE: f32 = 0.01;
+
+// Each sub-path of `path` as its start point and length.
+fn pieces(path: &Path) -> Vec<(Point, f32)> {
+    let mut pieces = Vec::new();
+    let mut builder = Path::builder();
+    let mut start = point(0.0, 0.0);
+    for event in path.iter() {
+        if let Event::Begin { at } = event {
+            start = at;
+        }
+        builder.path_event(event);
+        if let Event::End { .. } = event {
+            let piece = std::mem::replace(&mut builder, Path::builder()).build();
+            pieces.push((start, approximate_length(&piece, TOLERANCE)));
+        }
+    }
+    pieces
+}
+
+fn assert_pieces(path: &Path, expected: &[(Point, f32)]) {
+    let actual = pieces(path);
+    assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+    for ((p, length), (expected_p, expected_length)) in actual.iter().zip(expected) {
+        assert!((*p - *expected_p).length() < 1e-3, "{:?}", actual);
+        assert!((length - expected_length).abs() < 0.05, "{:?}", actual);
+    }
+}
+
+fn line(from: Point, to: Point) -> Path {
+    let mut builder = Path::builder();
+    builder.begin(from);
+    builder.line_to(to);
+    builder.end(false);
+    builder.build()
+}
+
+#[test]
+fn dashes_follow_the_pattern_and_offset() {
+    let path = line(point(0.0, 0.0), point(20.0, 0.0));
+    let dashed = dash(&path, &DashPattern::new(&[4.0, 2.0]), TOLERANCE);
+    assert_pieces(
+        &dashed,
+        &[
+            (point(0.0, 0.0), 4.0),
+            (point(6.0, 0.0), 4.0),
+            (point(12.0, 0.0), 4.0),
+            (point(18.0, 0.0), 2.0),
+        ],
+    );
+
+    // An odd pattern repeats, and the offset shifts it back.
+    let pattern = DashPattern::new(&[3.0]).with_offset(1.0);
+    assert_pieces(
+        &dash(&path, &pattern, TOLERANCE),
+        &[
+            (point(0.0, 0.0), 2.0),
+            (point(5.0, 0.0), 3.0),
+            (point(11.0, 0.0), 3.0),
+            (point(17.0, 0.0), 3.0),
+        ],
+    );
+}
+
+#[test]
+fn dashes_follow_curves() {
+    let mut builder = Path::builder();
+    builder.add_circle(point(0.0, 0.0), 10.0, Winding::Positive);
+    let circle = builder.build();
+    let circumference = 2.0 * std::f32::consts::PI * 10.0;
+    // Four dashes of a quarter circle each, minus the gaps.
+    let quarter = circumference / 4.0;
+    let dashed = dash(&circle, &DashPattern::new(&[quarter - 1.0, 1.0]), TOLERANCE);
+    let pieces = pieces(&dashed);
+    assert_eq!(pieces.len(), 4);
+    for (_, length) in pieces {
+        assert!((length - (quarter - 1.0)).abs() < 0.05);
+    }
+}
+
+#[test]
+fn closed_subpaths_join_across_their_start() {
+    let mut builder = Path::builder();
+    builder.add_rectangle(
+        &Box2D::new(point(0.0, 0.0), point(10.0, 10.0)),
+        Winding::Positive,
+    );
+    builder.begin(point(0.0, 20.0));
+    builder.line_to(point(10.0, 20.0));
+    builder.end(false);
+    let path = builder.build();
+    // The square is 40 long: dashes at 0..5, 10..15, 20..25 and 30..40,
+    // the last running on into the first. The line starts over.
+    let pattern = DashPattern::new(&[5.0, 5.0, 5.0, 5.0, 5.0, 5.0, 10.0, 0.0]);
+    assert_pieces(
+        &dash(&path, &pattern, TOLERANCE),
+        &[
+            (point(10.0, 0.0), 5.0),
+            (point(10.0, 10.0), 5.0),
+            (point(0.0, 10.0), 15.0),
+            (point(0.0, 20.0), 5.0),
+        ],
+    );
+}
+
+#[test]
+fn zero_length_dashes_are_points() {
+    let path = line(point(0.0, 0.0), point(10.0, 0.0));
+    let dashed = dash(&path, &DashPattern::new(&[0.0, 5.0]), TOLERANCE);
+    assert_pieces(
+        &dashed,
+        &[
+            (point(0.0, 0.0), 0.0),
+            (point(5.0, 0.0), 0.0),
+            (point(10.0, 0.0), 0.0),
+        ],
+    );
+}
+
+#[test]
+fn invalid_patterns_are_solid() {
+    let path = line(point(0.0, 0.0), point(10.0, 0.0));
+    for dashes in [&[][..], &[0.0, 0.0], &[1.0, -1.0], &[f32::NAN]] {
+        let dashed = dash(&path, &DashPattern::new(dashes), TOLERANCE);
+        assert_pieces(&dashed, &[(point(0.0, 0.0), 10.0)]);
+    }
+}
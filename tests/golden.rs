@@ -0,0 +1,1063 @@
+//! Renders reference scenes headlessly and compares them with the PNGs in
+//! `tests/golden/`.
+//!
+//! Run with `UPDATE_GOLDEN=1` to write the references from the current
+//! output instead. On a mismatch the actual image and a diff, with the
+//! differing pixels in red, are written next to the test binaries' temporary
+//! directory and their paths are printed.
+//!
+//! Machines without any adapter, not even a software one, skip the checks.
+
+use std::fs::File;
+use std::path::PathBuf;
+
+use lyon::math::{point, vector, Angle, Box2D, Transform};
+use lyon::path::builder::BorderRadii;
+use lyon::path::{Path, Winding};
+use lyon::tessellation::{FillRule, LineCap, LineJoin, StrokeOptions};
+
+use wgpu_lyon::canvas::Canvas2d;
+use wgpu_lyon::headless::{HeadlessError, HeadlessOptions, HeadlessRenderer};
+use wgpu_lyon::image::Image;
+use wgpu_lyon::paint::{ImagePaint, LinearGradient, RadialGradient, Spread};
+use wgpu_lyon::scene::{Clip, Group, Instance, Scene, Shape};
+use wgpu_lyon::shapes::{self, arrow};
+use wgpu_lyon::svg;
+use wgpu_lyon::{RenderOptions, UnsupportedSampleCount};
+
+const SIZE: u32 = 256;
+// Largest difference allowed on any channel before a pixel counts as
+// different. Leaves room for rasterizers disagreeing on edge pixels.
+const CHANNEL_TOLERANCE: u8 = 8;
+// Fraction of the pixels allowed to be different.
+const MAX_DIFFERENT: f64 = 0.002;
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn render(scene: &Scene, options: &HeadlessOptions) -> Option<Image> {
+    let renderer = pollster::block_on(HeadlessRenderer::new(SIZE, SIZE, options));
+    match renderer {
+        Ok(mut renderer) => {
+            let camera = renderer.default_camera();
+            Some(renderer.render(scene, &camera))
+        }
+        Err(e) => {
+            eprintln!("Skipping golden image check: {}", e);
+            None
+        }
+    }
+}
+
+fn load_png(path: &std::path::Path) -> Option<Image> {
+    let file = File::open(path).ok()?;
+    Some(Image::from_png(file).unwrap())
+}
+
+// Returns the number of differing pixels and an image showing them in red
+// over a faded copy of the expected image.
+fn diff(expected: &Image, actual: &Image) -> (usize, Image) {
+    let mut different = 0;
+    let mut pixels = Vec::with_capacity(expected.pixels.len());
+    for (e, a) in expected
+        .pixels
+        .chunks_exact(4)
+        .zip(actual.pixels.chunks_exact(4))
+    {
+        let is_different = e
+            .iter()
+            .zip(a)
+            .any(|(e, a)| e.abs_diff(*a) > CHANNEL_TOLERANCE);
+        if is_different {
+            different += 1;
+            pixels.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let luma = (e[0] as u32 * 3 + e[1] as u32 * 6 + e[2] as u32) / 10;
+            let faded = (170 + luma / 3) as u8;
+            pixels.extend_from_slice(&[faded, faded, faded, 255]);
+        }
+    }
+    let image = Image {
+        width: expected.width,
+        height: expected.height,
+        pixels,
+    };
+    (different, image)
+}
+
+fn check(name: &str, scene: &Scene) {
+    check_with(name, scene, &HeadlessOptions::default());
+}
+
+fn check_with(name: &str, scene: &Scene, options: &HeadlessOptions) {
+    let actual = match render(scene, options) {
+        Some(image) => image,
+        None => return,
+    };
+
+    let golden = golden_dir().join(format!("{}.png", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(golden_dir()).unwrap();
+        actual.save_png(&golden).unwrap();
+        return;
+    }
+
+    let out_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    std::fs::create_dir_all(&out_dir).unwrap();
+    let actual_path = out_dir.join(format!("{}.actual.png", name));
+
+    let expected = match load_png(&golden) {
+        Some(image) => image,
+        None => {
+            actual.save_png(&actual_path).unwrap();
+            panic!(
+                "No reference image at {:?}, output written to {:?}. Run with UPDATE_GOLDEN=1 to accept it.",
+                golden, actual_path
+            );
+        }
+    };
+    assert_eq!(
+        (expected.width, expected.height),
+        (actual.width, actual.height),
+        "{}: size changed",
+        name
+    );
+
+    let (different, diff_image) = diff(&expected, &actual);
+    let allowed = ((SIZE * SIZE) as f64 * MAX_DIFFERENT) as usize;
+    if different > allowed {
+        let diff_path = out_dir.join(format!("{}.diff.png", name));
+        actual.save_png(&actual_path).unwrap();
+        diff_image.save_png(&diff_path).unwrap();
+        panic!(
+            "{}: {} pixels differ (at most {} allowed). Actual: {:?}, diff: {:?}",
+            name, different, allowed, actual_path, diff_path
+        );
+    }
+}
+
+fn white_scene() -> Scene {
+    let mut scene = Scene::new();
+    scene.background = [1.0, 1.0, 1.0, 1.0];
+    scene
+}
+
+// A five-pointed star drawn in one stroke, so its center is wound twice.
+fn star() -> Path {
+    let mut builder = Path::builder();
+    for i in 0..5 {
+        let angle = std::f32::consts::PI * (-0.5 + 0.8 * i as f32);
+        let p = point(128.0, 128.0) + vector(angle.cos(), angle.sin()) * 100.0;
+        if i == 0 {
+            builder.begin(p);
+        } else {
+            builder.line_to(p);
+        }
+    }
+    builder.close();
+    builder.build()
+}
+
+fn self_intersecting(fill_rule: FillRule) -> Scene {
+    let mut scene = white_scene();
+    scene.add(
+        Shape::new(star())
+            .with_fill([0.2, 0.3, 0.8, 1.0])
+            .with_fill_rule(fill_rule),
+    );
+    scene
+}
+
+#[test]
+fn arrow_fill_and_stroke() {
+    let mut scene = white_scene();
+    scene.add(
+        Shape::new(arrow())
+            .with_fill([1.0, 1.0, 0.0, 1.0])
+            .with_stroke(
+                [1.0, 0.5, 0.0, 1.0],
+                StrokeOptions::default()
+                    .with_line_width(0.1)
+                    .with_line_cap(LineCap::Round)
+                    .with_line_join(LineJoin::Round),
+            )
+            .with_transform(Transform::scale(80.0, 80.0).then_translate(vector(108.0, 128.0))),
+    );
+    check("arrow", &scene);
+}
+
+#[test]
+fn curves() {
+    let mut builder = Path::builder();
+    builder.begin(point(0.0, 0.0));
+    builder.cubic_bezier_to(point(0.5, -0.8), point(1.0, -1.3), point(2.0, 1.0));
+    builder.line_to(point(2.0, 1.1));
+    builder.cubic_bezier_to(point(1.0, -1.2), point(0.5, -0.7), point(0.0, 0.1));
+    builder.close();
+    let ribbon = builder.build();
+
+    let mut builder = Path::builder();
+    builder.begin(point(30.0, 230.0));
+    builder.quadratic_bezier_to(point(128.0, 120.0), point(226.0, 230.0));
+    builder.end(false);
+    let arch = builder.build();
+
+    let mut scene = white_scene();
+    scene.add(
+        Shape::new(ribbon)
+            .with_fill([1.0, 0.0, 0.0, 1.0])
+            .with_stroke(
+                [0.0, 0.0, 0.0, 1.0],
+                StrokeOptions::default().with_line_width(0.02),
+            )
+            .with_transform(Transform::scale(100.0, 100.0).then_translate(vector(28.0, 110.0))),
+    );
+    scene.add(
+        Shape::new(arch).with_stroke(
+            [0.1, 0.5, 0.2, 1.0],
+            StrokeOptions::default()
+                .with_line_width(12.0)
+                .with_line_cap(LineCap::Round),
+        ),
+    );
+    check("curves", &scene);
+}
+
+#[test]
+fn rounded_rectangles() {
+    let mut scene = white_scene();
+    let radii = [
+        BorderRadii::new(10.0),
+        BorderRadii {
+            top_left: 0.0,
+            top_right: 30.0,
+            bottom_left: 30.0,
+            bottom_right: 0.0,
+        },
+        BorderRadii::new(50.0),
+    ];
+    for (i, radii) in radii.iter().enumerate() {
+        let mut builder = Path::builder();
+        let min = point(20.0 + 80.0 * i as f32, 40.0);
+        builder.add_rounded_rectangle(
+            &Box2D::new(min, min + vector(60.0, 170.0)),
+            radii,
+            Winding::Positive,
+        );
+        scene.add(
+            Shape::new(builder.build())
+                .with_fill([0.9, 0.8, 0.4, 1.0])
+                .with_stroke(
+                    [0.3, 0.2, 0.1, 1.0],
+                    StrokeOptions::default().with_line_width(4.0),
+                ),
+        );
+    }
+    check("rounded_rectangles", &scene);
+}
+
+#[test]
+fn self_intersecting_non_zero() {
+    check(
+        "self_intersecting_non_zero",
+        &self_intersecting(FillRule::NonZero),
+    );
+}
+
+#[test]
+fn self_intersecting_even_odd() {
+    check(
+        "self_intersecting_even_odd",
+        &self_intersecting(FillRule::EvenOdd),
+    );
+}
+
+#[test]
+fn feathered_edges() {
+    let mut scene = self_intersecting(FillRule::NonZero);
+    scene.add(
+        Shape::new(arrow())
+            .with_stroke(
+                [0.8, 0.2, 0.1, 1.0],
+                StrokeOptions::default()
+                    .with_line_width(0.08)
+                    .with_line_cap(LineCap::Round)
+                    .with_line_join(LineJoin::Round),
+            )
+            .with_transform(Transform::scale(60.0, 60.0).then_translate(vector(98.0, 40.0))),
+    );
+    let options = HeadlessOptions {
+        render: RenderOptions {
+            sample_count: 1,
+            feathering: true,
+        },
+        ..Default::default()
+    };
+    check_with("feathered_edges", &scene, &options);
+}
+
+#[test]
+fn translucent_overlap() {
+    let circle = |x: f32, y: f32| {
+        let mut builder = Path::builder();
+        builder.add_circle(point(x, y), 50.0, Winding::Positive);
+        builder.build()
+    };
+
+    let mut scene = white_scene();
+    // Translucent colors and shape opacity.
+    scene.add(Shape::new(circle(80.0, 70.0)).with_fill([1.0, 0.0, 0.0, 0.5]));
+    scene.add(Shape::new(circle(130.0, 70.0)).with_fill([0.0, 0.0, 1.0, 0.5]));
+    scene.add(
+        Shape::new(circle(180.0, 70.0))
+            .with_fill([0.0, 0.6, 0.0, 1.0])
+            .with_opacity(0.5),
+    );
+
+    // Opacity of nested groups multiplies.
+    let outer = scene.add_group(Group::new().with_opacity(0.5));
+    let inner = scene.add_group(Group::new().with_parent(outer).with_opacity(0.5));
+    scene.add(
+        Shape::new(circle(100.0, 180.0))
+            .with_fill([0.2, 0.2, 0.2, 1.0])
+            .with_group(outer),
+    );
+    scene.add(
+        Shape::new(circle(160.0, 180.0))
+            .with_fill([0.2, 0.2, 0.2, 1.0])
+            .with_stroke(
+                [0.0, 0.0, 0.0, 1.0],
+                StrokeOptions::default().with_line_width(6.0),
+            )
+            .with_group(inner),
+    );
+    check("translucent_overlap", &scene);
+}
+
+#[test]
+fn gradients() {
+    let rect = |x: f32, y: f32, w: f32, h: f32| {
+        let mut builder = Path::builder();
+        builder.add_rectangle(
+            &Box2D::new(point(x, y), point(x + w, y + h)),
+            Winding::Positive,
+        );
+        builder.build()
+    };
+
+    let mut scene = white_scene();
+    // The same three stops over the middle third of each bar, spread three
+    // ways.
+    for (i, spread) in [Spread::Pad, Spread::Repeat, Spread::Reflect]
+        .into_iter()
+        .enumerate()
+    {
+        let y = 10.0 + 30.0 * i as f32;
+        let gradient = LinearGradient::new(point(88.0, 0.0), point(168.0, 0.0))
+            .with_stop(0.0, [1.0, 0.0, 0.0, 1.0])
+            .with_stop(0.5, [1.0, 1.0, 0.0, 1.0])
+            .with_stop(1.0, [0.0, 0.0, 1.0, 0.0])
+            .with_spread(spread);
+        scene.add(Shape::new(rect(8.0, y, 240.0, 24.0)).with_fill(gradient));
+    }
+
+    // Off-center focal point, and a hard stop.
+    let radial = RadialGradient::new(point(70.0, 170.0), 60.0)
+        .with_focal(point(50.0, 150.0))
+        .with_stop(0.0, [1.0, 1.0, 1.0, 1.0])
+        .with_stop(0.6, [0.2, 0.4, 0.9, 1.0])
+        .with_stop(0.6, [0.1, 0.1, 0.4, 1.0])
+        .with_stop(1.0, [0.0, 0.0, 0.0, 1.0]);
+    scene.add(Shape::new(rect(10.0, 110.0, 120.0, 120.0)).with_fill(radial));
+
+    // Gradient stroke and gradient transform, with shape opacity.
+    let mut builder = Path::builder();
+    builder.add_circle(point(0.0, 0.0), 1.0, Winding::Positive);
+    let stroke = LinearGradient::new(point(-1.0, 0.0), point(1.0, 0.0))
+        .with_stop(0.0, [0.0, 0.6, 0.2, 1.0])
+        .with_stop(1.0, [0.6, 0.0, 0.6, 1.0])
+        .with_transform(Transform::rotation(lyon::math::Angle::degrees(90.0)));
+    scene.add(
+        Shape::new(builder.build())
+            .with_stroke(stroke, StrokeOptions::default().with_line_width(0.3))
+            .with_transform(Transform::scale(50.0, 50.0).then_translate(vector(190.0, 170.0)))
+            .with_opacity(0.7),
+    );
+    check("gradients", &scene);
+}
+
+#[test]
+fn images() {
+    // A 16x16 badge, a disc fading out at its rim, through a PNG round trip.
+    let mut badge = Image {
+        width: 16,
+        height: 16,
+        pixels: Vec::new(),
+    };
+    for y in 0..16 {
+        for x in 0..16 {
+            let d = ((x as f32 - 7.5).powi(2) + (y as f32 - 7.5).powi(2)).sqrt();
+            let alpha = ((8.0 - d) * 128.0).clamp(0.0, 255.0) as u8;
+            let color = if x < 8 { [230, 60, 30] } else { [30, 90, 200] };
+            badge
+                .pixels
+                .extend_from_slice(&[color[0], color[1], color[2], alpha]);
+        }
+    }
+    let mut bytes = Vec::new();
+    badge.write_png(&mut bytes).unwrap();
+    let badge = Image::from_png(bytes.as_slice()).unwrap();
+
+    // A 6x6 tile with a diagonal line, for hatching.
+    let mut hatch = Image {
+        width: 6,
+        height: 6,
+        pixels: Vec::new(),
+    };
+    for y in 0..6 {
+        for x in 0..6 {
+            let on = (x + y) % 6 < 2;
+            hatch.pixels.extend_from_slice(if on {
+                &[40, 40, 40, 255]
+            } else {
+                &[0, 0, 0, 0]
+            });
+        }
+    }
+
+    let mut scene = white_scene();
+    let badge = scene.add_image(badge);
+    let hatch = scene.add_image(hatch);
+
+    // The badge scaled up in a larger square: nothing is drawn around it.
+    let mut builder = Path::builder();
+    builder.add_rectangle(
+        &Box2D::new(point(10.0, 10.0), point(118.0, 118.0)),
+        Winding::Positive,
+    );
+    let logo = ImagePaint::new(badge)
+        .with_transform(Transform::scale(6.0, 6.0).then_translate(vector(16.0, 16.0)));
+    scene.add(
+        Shape::new(builder.build())
+            .with_fill(logo)
+            .with_stroke([0.0, 0.0, 0.0, 1.0], StrokeOptions::default()),
+    );
+
+    // The badge tiled at half opacity, through the shape's transform.
+    let mut builder = Path::builder();
+    builder.add_rectangle(
+        &Box2D::new(point(0.0, 0.0), point(64.0, 54.0)),
+        Winding::Positive,
+    );
+    scene.add(
+        Shape::new(builder.build())
+            .with_fill(ImagePaint::new(badge).with_repeat(true))
+            .with_transform(Transform::scale(1.5, 2.0).then_translate(vector(136.0, 10.0)))
+            .with_opacity(0.5),
+    );
+
+    // A hatched disc with a rotated pattern, and a hatched stroke.
+    let mut builder = Path::builder();
+    builder.add_circle(point(64.0, 190.0), 54.0, Winding::Positive);
+    let pattern = ImagePaint::new(hatch)
+        .with_repeat(true)
+        .with_transform(Transform::rotation(lyon::math::Angle::degrees(90.0)));
+    scene.add(Shape::new(builder.build()).with_fill(pattern.clone()));
+    let mut builder = Path::builder();
+    builder.add_circle(point(190.0, 190.0), 40.0, Winding::Positive);
+    scene.add(
+        Shape::new(builder.build())
+            .with_stroke(pattern, StrokeOptions::default().with_line_width(24.0)),
+    );
+    check("images", &scene);
+}
+
+#[test]
+fn svg_document() {
+    let mut scene = white_scene();
+    svg::import(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="256" height="256" viewBox="0 0 128 128">
+            <rect x="4" y="4" width="56" height="40" rx="12" ry="6" fill="#e9c46a" stroke="#264653"/>
+            <ellipse cx="96" cy="24" rx="26" ry="14" fill="#2a9d8f" transform="rotate(-20 96 24)"/>
+            <g stroke="#e76f51" stroke-width="3" fill="none" stroke-linejoin="round">
+                <polyline points="6 100 24 60 42 100 60 60"/>
+                <g transform="translate(68 56) scale(0.5)" opacity="0.5" stroke-linecap="round">
+                    <line x1="10" y1="10" x2="100" y2="10"/>
+                    <polygon points="10,30 100,30 55,110" fill="#264653"/>
+                </g>
+            </g>
+            <path d="M70 100 h40 a10 10 0 0 1 0 20 h-40 z M80 106 v8 h20 v-8 z"
+                  style="fill: #264653; fill-rule: evenodd"/>
+        </svg>"##,
+        &mut scene,
+    )
+    .unwrap();
+    check("svg_document", &scene);
+}
+
+#[test]
+fn nested_subpaths() {
+    let mut ctx = Canvas2d::new();
+    ctx.set_fill_style([0.2, 0.3, 0.8, 1.0]);
+    // Squares inside squares, like the counters of a glyph. Both turn the
+    // same way on the left, so only even-odd leaves a hole; on the right the
+    // inner one turns back, which leaves a hole with either rule.
+    let square = |ctx: &mut Canvas2d, x: f32, y: f32, size: f32, reversed: bool| {
+        let mut corners = [(x, y), (x + size, y), (x + size, y + size), (x, y + size)];
+        if reversed {
+            corners.reverse();
+        }
+        ctx.move_to(corners[0].0, corners[0].1);
+        for (cx, cy) in &corners[1..] {
+            ctx.line_to(*cx, *cy);
+        }
+        ctx.close_path();
+    };
+    for (i, rule) in [FillRule::NonZero, FillRule::EvenOdd]
+        .into_iter()
+        .enumerate()
+    {
+        let y = 8.0 + 124.0 * i as f32;
+        for (j, reversed) in [false, true].into_iter().enumerate() {
+            let x = 8.0 + 124.0 * j as f32;
+            ctx.begin_path();
+            square(&mut ctx, x, y, 116.0, false);
+            square(&mut ctx, x + 29.0, y + 29.0, 58.0, reversed);
+            ctx.fill_with_rule(rule);
+        }
+    }
+    check("nested_subpaths", &ctx.into_scene());
+}
+
+#[test]
+fn primitives() {
+    let mut scene = white_scene();
+    let cell = |i: usize| point(43.0 + 85.0 * (i % 3) as f32, 43.0 + 85.0 * (i / 3) as f32);
+    let radii = BorderRadii {
+        top_left: 20.0,
+        top_right: 5.0,
+        bottom_left: 0.0,
+        bottom_right: 10.0,
+    };
+    let square = |c| Box2D::new(c - vector(30.0, 30.0), c + vector(30.0, 30.0));
+    let shapes = [
+        shapes::rect(square(cell(0))),
+        shapes::rounded_rect(square(cell(1)), &radii),
+        shapes::circle(cell(2), 30.0),
+        shapes::ellipse(cell(3), vector(35.0, 15.0), Angle::degrees(30.0)),
+        shapes::regular_polygon(cell(4), 32.0, 6),
+        shapes::star(cell(5), 34.0, 14.0, 5),
+        shapes::pie(cell(6), 32.0, Angle::degrees(-90.0), Angle::degrees(270.0)),
+        shapes::ring(cell(7), 32.0, 18.0),
+    ];
+    for shape in shapes {
+        scene.add(shape.with_fill([0.2, 0.5, 0.8, 1.0]).with_stroke(
+            [0.1, 0.2, 0.4, 1.0],
+            StrokeOptions::default().with_line_width(3.0),
+        ));
+    }
+    scene.add(
+        shapes::arc(cell(8), 30.0, Angle::degrees(0.0), Angle::degrees(225.0)).with_stroke(
+            [0.8, 0.3, 0.1, 1.0],
+            StrokeOptions::default()
+                .with_line_width(6.0)
+                .with_line_cap(LineCap::Round),
+        ),
+    );
+    check("primitives", &scene);
+}
+
+#[test]
+fn clipping() {
+    let mut scene = white_scene();
+    // Stripes clipped to a circle, then a frame clipped to the even-odd
+    // star within that, then more stripes back in the circle only.
+    let circle = scene.add_group(
+        Group::new().with_clip(Clip::new(shapes::circle(point(128.0, 128.0), 100.0).path)),
+    );
+    let stripe = |scene: &mut Scene, i: usize, color| {
+        let min = point(8.0 + 30.0 * i as f32, 0.0);
+        scene.add(
+            shapes::rect(Box2D::new(min, min + vector(20.0, 256.0)))
+                .with_fill(color)
+                .with_group(circle),
+        );
+    };
+    for i in 0..4 {
+        stripe(&mut scene, i, [0.9, 0.6, 0.2, 1.0]);
+    }
+    let mut star_group = Group::new()
+        .with_parent(circle)
+        .with_clip(Clip::new(star()).with_fill_rule(FillRule::EvenOdd));
+    star_group.opacity = 0.8;
+    let star_group = scene.add_group(star_group);
+    scene.add(
+        shapes::rect(Box2D::new(point(0.0, 0.0), point(256.0, 256.0)))
+            .with_fill([0.2, 0.3, 0.8, 1.0])
+            .with_group(star_group),
+    );
+    for i in 4..9 {
+        stripe(&mut scene, i, [0.3, 0.7, 0.3, 1.0]);
+    }
+    // Outside every clip.
+    scene.add(
+        shapes::rect(Box2D::new(point(0.0, 236.0), point(256.0, 256.0)))
+            .with_fill([0.5, 0.5, 0.5, 1.0]),
+    );
+    check("clipping", &scene);
+}
+
+#[test]
+fn canvas_clip() {
+    let mut ctx = Canvas2d::new();
+    ctx.save();
+    ctx.begin_path();
+    ctx.arc(128.0, 128.0, 100.0, 0.0, 2.0 * std::f32::consts::PI, false);
+    ctx.clip();
+    ctx.set_fill_style([0.9, 0.6, 0.2, 1.0]);
+    ctx.fill_rect(0.0, 0.0, 128.0, 256.0);
+    ctx.save();
+    ctx.begin_path();
+    ctx.rect(0.0, 100.0, 256.0, 56.0);
+    ctx.clip();
+    ctx.set_fill_style([0.2, 0.3, 0.8, 1.0]);
+    ctx.fill_rect(0.0, 0.0, 256.0, 256.0);
+    ctx.restore();
+    // Back in the circle only.
+    ctx.set_fill_style([0.3, 0.7, 0.3, 1.0]);
+    ctx.fill_rect(160.0, 0.0, 40.0, 256.0);
+    ctx.restore();
+    // Not clipped at all.
+    ctx.set_fill_style([0.5, 0.5, 0.5, 1.0]);
+    ctx.fill_rect(0.0, 236.0, 256.0, 20.0);
+    check("canvas_clip", &ctx.into_scene());
+}
+
+#[test]
+fn svg_clip_path_union() {
+    let mut scene = white_scene();
+    // The second square winds the other way. Merged into one path, their
+    // overlap would have a winding number of zero and be clipped away.
+    svg::import(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="256" height="256">
+            <clipPath id="squares">
+                <path d="M32 32 H160 V160 H32 Z"/>
+                <path d="M96 96 V224 H224 V96 Z"/>
+            </clipPath>
+            <rect width="256" height="256" fill="#264653" clip-path="url(#squares)"/>
+        </svg>"##,
+        &mut scene,
+    )
+    .unwrap();
+    let Some(image) = render(&scene, &HeadlessOptions::default()) else {
+        return;
+    };
+    let pixel = |x: u32, y: u32| {
+        let i = ((y * image.width + x) * 4) as usize;
+        image.pixels[i..i + 4].to_vec()
+    };
+    let outside = vec![255; 4];
+    assert_eq!(pixel(192, 64), outside);
+    let inside = pixel(64, 64);
+    assert_ne!(inside, outside);
+    assert_eq!(pixel(128, 128), inside, "the overlap is clipped away");
+    assert_eq!(pixel(192, 192), inside);
+}
+
+#[test]
+fn dashes() {
+    let mut ctx = Canvas2d::new();
+    // Gridlines, shifted by the offset on every other one.
+    ctx.set_stroke_style([0.5, 0.5, 0.5, 1.0]);
+    ctx.set_line_dash(&[8.0, 4.0]);
+    for i in 0..4 {
+        ctx.set_line_dash_offset(if i % 2 == 0 { 0.0 } else { 6.0 });
+        ctx.begin_path();
+        ctx.move_to(8.0, 16.0 + 16.0 * i as f32);
+        ctx.line_to(248.0, 16.0 + 16.0 * i as f32);
+        ctx.stroke();
+    }
+    // A curve, with the pattern in its own units under a scale.
+    ctx.save();
+    ctx.scale(2.0, 2.0);
+    ctx.set_stroke_style([0.2, 0.3, 0.8, 1.0]);
+    ctx.set_line_width(3.0);
+    ctx.set_line_dash(&[10.0, 5.0, 2.0]);
+    ctx.set_line_dash_offset(0.0);
+    ctx.begin_path();
+    ctx.move_to(8.0, 60.0);
+    ctx.bezier_curve_to(40.0, 20.0, 80.0, 100.0, 120.0, 40.0);
+    ctx.stroke();
+    ctx.restore();
+    // Dots.
+    ctx.set_stroke_style([0.8, 0.3, 0.1, 1.0]);
+    ctx.set_line_width(6.0);
+    ctx.set_line_cap(LineCap::Round);
+    ctx.set_line_dash(&[0.0, 12.0]);
+    ctx.begin_path();
+    ctx.move_to(14.0, 196.0);
+    ctx.line_to(242.0, 196.0);
+    ctx.stroke();
+    // A selection marquee, whose dash through the corner it starts at is
+    // joined rather than capped.
+    ctx.set_stroke_style([0.0, 0.0, 0.0, 1.0]);
+    ctx.set_line_width(2.0);
+    ctx.set_line_cap(LineCap::Butt);
+    ctx.set_line_dash(&[6.0, 4.0]);
+    ctx.set_line_dash_offset(3.0);
+    ctx.stroke_rect(16.0, 214.0, 224.0, 30.0);
+    check("dashes", &ctx.into_scene());
+}
+
+#[test]
+fn canvas_stretched_strokes() {
+    let mut ctx = Canvas2d::new();
+    // Stretched 4 times wider, so the sides of the circle are 4 times as
+    // thick as its top and bottom, and the dashes 4 times longer.
+    ctx.save();
+    ctx.scale(4.0, 1.0);
+    ctx.set_stroke_style([0.2, 0.3, 0.8, 1.0]);
+    ctx.set_line_width(4.0);
+    ctx.begin_path();
+    ctx.arc(32.0, 72.0, 24.0, 0.0, 2.0 * std::f32::consts::PI, false);
+    ctx.stroke();
+    ctx.set_stroke_style([0.8, 0.3, 0.1, 1.0]);
+    ctx.set_line_dash(&[6.0, 4.0]);
+    ctx.begin_path();
+    ctx.move_to(4.0, 132.0);
+    ctx.line_to(60.0, 132.0);
+    ctx.move_to(8.0, 148.0);
+    ctx.line_to(8.0, 244.0);
+    ctx.stroke();
+    ctx.restore();
+    // A turned square stretched sideways, skewing it: the stroke follows
+    // the skew instead of keeping an even width.
+    ctx.translate(176.0, 192.0);
+    ctx.scale(2.0, 1.0);
+    ctx.rotate(std::f32::consts::PI / 4.0);
+    ctx.set_stroke_style([0.3, 0.7, 0.3, 1.0]);
+    ctx.set_line_width(8.0);
+    ctx.stroke_rect(-24.0, -24.0, 48.0, 48.0);
+    check("canvas_stretched_strokes", &ctx.into_scene());
+}
+
+#[test]
+fn instances() {
+    let mut scene = white_scene();
+    // Arrow markers turning around a grid, tessellated once.
+    let mut markers = Vec::new();
+    for i in 0..5 {
+        for j in 0..4 {
+            let angle = Angle::degrees(18.0 * (i + 5 * j) as f32);
+            let transform = Transform::rotation(angle)
+                .then_scale(16.0, 16.0)
+                .then_translate(vector(32.0 + 48.0 * i as f32, 32.0 + 48.0 * j as f32));
+            let shade = 0.4 + 0.15 * j as f32;
+            markers.push(Instance::new(transform).with_tint([shade, 1.0, 1.0 - shade, 1.0]));
+        }
+    }
+    scene.add(
+        Shape::new(arrow())
+            .with_fill([1.0, 0.6, 0.2, 1.0])
+            .with_stroke(
+                [0.2, 0.2, 0.2, 1.0],
+                StrokeOptions::default().with_line_width(0.12),
+            )
+            .with_instances(markers),
+    );
+    // The gradient moves with each copy, which fades out along the row.
+    let gradient = LinearGradient::new(point(-12.0, 0.0), point(12.0, 0.0))
+        .with_stop(0.0, [0.2, 0.3, 0.8, 1.0])
+        .with_stop(1.0, [0.9, 0.9, 1.0, 1.0]);
+    let copies = (0..5)
+        .map(|i| {
+            Instance::new(Transform::translation(32.0 + 48.0 * i as f32, 224.0)).with_tint([
+                1.0,
+                1.0,
+                1.0,
+                1.0 - 0.2 * i as f32,
+            ])
+        })
+        .collect();
+    scene.add(
+        shapes::circle(point(0.0, 0.0), 16.0)
+            .with_fill(gradient)
+            .with_instances(copies),
+    );
+    check("instances", &scene);
+}
+
+#[test]
+fn many_shapes() {
+    let mut scene = white_scene();
+    // Rows of circles, each covering part of the one before it, with every
+    // other one sharing a gradient across the whole scene. Shapes in
+    // different rows don't touch, so the rows can share draw calls. More
+    // vertices than 16-bit indices can reach.
+    let gradient = LinearGradient::new(point(0.0, 0.0), point(256.0, 256.0))
+        .with_stop(0.0, [0.9, 0.8, 0.2, 1.0])
+        .with_stop(1.0, [0.6, 0.2, 0.8, 1.0]);
+    for j in 0..19 {
+        for i in 0..63 {
+            let center = point(4.0 + 4.0 * i as f32, 8.0 + 13.5 * j as f32);
+            let shape = shapes::circle(center, 5.5).with_stroke(
+                [0.1, 0.1, 0.1, 1.0],
+                StrokeOptions::default().with_line_width(0.75),
+            );
+            scene.add(match i % 2 {
+                0 => shape.with_fill(gradient.clone()),
+                _ => shape.with_fill([0.2, 0.5 + 0.02 * j as f32, 0.9, 1.0]),
+            });
+        }
+    }
+    let options = HeadlessOptions {
+        render: RenderOptions {
+            sample_count: 1,
+            feathering: true,
+        },
+        ..Default::default()
+    };
+    check_with("many_shapes", &scene, &options);
+}
+
+#[test]
+fn empty_images_are_rejected() {
+    for (width, height) in [(0, SIZE), (SIZE, 0), (0, 0)] {
+        let result = pollster::block_on(HeadlessRenderer::new(
+            width,
+            height,
+            &HeadlessOptions::default(),
+        ));
+        assert!(
+            matches!(result, Err(HeadlessError::InvalidSize { .. })),
+            "{}x{}",
+            width,
+            height
+        );
+    }
+}
+
+#[test]
+fn one_renderer_many_scenes() {
+    let options = HeadlessOptions::default();
+    // As many edits each, so their own counts of them are the same.
+    let scenes: Vec<Scene> = [[0.9, 0.3, 0.2, 1.0], [0.2, 0.4, 0.9, 1.0]]
+        .into_iter()
+        .enumerate()
+        .map(|(i, color)| {
+            let mut scene = white_scene();
+            let center = point(64.0 + 128.0 * i as f32, 128.0);
+            scene.add(shapes::circle(center, 48.0).with_fill(color));
+            scene
+        })
+        .collect();
+    let frames: Vec<Image> = {
+        let mut renderer = match pollster::block_on(HeadlessRenderer::new(SIZE, SIZE, &options)) {
+            Ok(renderer) => renderer,
+            Err(e) => {
+                eprintln!("Skipping golden image check: {}", e);
+                return;
+            }
+        };
+        let camera = renderer.default_camera();
+        scenes
+            .iter()
+            .map(|scene| renderer.render(scene, &camera))
+            .collect()
+    };
+    for (i, (scene, frame)) in scenes.iter().zip(&frames).enumerate() {
+        let expected = render(scene, &options).unwrap();
+        let (different, _) = diff(&expected, frame);
+        assert_eq!(different, 0, "scene {}: {} pixels differ", i, different);
+    }
+}
+
+#[test]
+fn transparent_background() {
+    let color = [0.2, 0.4, 0.9];
+    let scene = |alpha: f32| {
+        let mut scene = Scene::new();
+        scene.background = [color[0], color[1], color[2], alpha];
+        scene.add(shapes::circle(point(128.0, 128.0), 64.0).with_fill([1.0, 0.0, 0.0, 0.5]));
+        scene
+    };
+    let (Some(opaque), Some(translucent), Some(clear)) = (
+        render(&scene(1.0), &HeadlessOptions::default()),
+        render(&scene(0.5), &HeadlessOptions::default()),
+        render(&scene(0.0), &HeadlessOptions::default()),
+    ) else {
+        return;
+    };
+    let pixel = |image: &Image, x: u32, y: u32| {
+        let i = ((y * image.width + x) * 4) as usize;
+        [
+            image.pixels[i],
+            image.pixels[i + 1],
+            image.pixels[i + 2],
+            image.pixels[i + 3],
+        ]
+    };
+    let close = |a: [u8; 4], b: [u8; 4]| a.iter().zip(&b).all(|(a, b)| a.abs_diff(*b) <= 2);
+    // Colors are straight, the same whatever their alpha.
+    let background = pixel(&translucent, 8, 8);
+    let mut expected = pixel(&opaque, 8, 8);
+    expected[3] = 128;
+    assert!(close(background, expected), "{:?}", background);
+    assert!(close(pixel(&clear, 128, 128), [255, 0, 0, 128]));
+    // Along the antialiased edge too.
+    for x in 190..194 {
+        let [r, g, b, a] = pixel(&clear, x, 128);
+        if a > 0 {
+            assert!(close([r, g, b, 0], [255, 0, 0, 0]), "{:?}", [r, g, b, a]);
+        }
+    }
+}
+
+#[test]
+fn unsupported_sample_counts_are_rejected() {
+    for sample_count in [0, 2, 8, 16] {
+        let options = HeadlessOptions {
+            render: RenderOptions {
+                sample_count,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        match pollster::block_on(HeadlessRenderer::new(SIZE, SIZE, &options)) {
+            Err(HeadlessError::UnsupportedSampleCount(e)) => {
+                assert_eq!(e, UnsupportedSampleCount(sample_count))
+            }
+            Err(HeadlessError::NoAdapter) => return,
+            Err(e) => panic!("{} samples: {}", sample_count, e),
+            Ok(_) => panic!("{} samples per pixel accepted", sample_count),
+        }
+    }
+}
+
+// The scene of `edits_between_frames` after the first `step` edits.
+fn edit(scene: &mut Scene, step: usize) {
+    let ids: Vec<_> = scene.iter().map(|(id, _)| id).collect();
+    match step {
+        0 => {
+            let clip = scene.add_group(
+                Group::new().with_clip(Clip::new(shapes::circle(point(192.0, 64.0), 48.0).path)),
+            );
+            for i in 0..8 {
+                scene.add(
+                    shapes::circle(point(16.0 + 32.0 * i as f32, 64.0), 20.0)
+                        .with_fill([0.2, 0.4, 0.9, 1.0])
+                        .with_stroke(
+                            [0.1, 0.1, 0.1, 1.0],
+                            StrokeOptions::default().with_line_width(2.0),
+                        )
+                        .with_group(clip),
+                );
+            }
+        }
+        // The same number of vertices, written in place, and the same
+        // indices in another order.
+        1 => {
+            scene.get_mut(ids[3]).unwrap().z_index = 1;
+            let fill = scene.get_mut(ids[6]).unwrap().fill.as_mut().unwrap();
+            fill.paint = [0.9, 0.3, 0.2, 1.0].into();
+            scene.get_mut(ids[7]).unwrap().transform = Transform::translation(-8.0, 32.0);
+        }
+        // Vertices freed, and more added than there were.
+        2 => {
+            scene.remove(ids[1]);
+            scene.add(
+                shapes::star(point(64.0, 160.0), 60.0, 24.0, 12)
+                    .with_fill([0.9, 0.8, 0.2, 1.0])
+                    .with_stroke(
+                        [0.1, 0.1, 0.1, 1.0],
+                        StrokeOptions::default().with_line_width(3.0),
+                    ),
+            );
+        }
+        // More vertices than fit the buffer, or 16-bit indices, and more
+        // instances and paints.
+        3 => {
+            let dots = (0..96)
+                .map(|i| {
+                    let t = Transform::translation(8.0 + 2.5 * i as f32, 120.0 + (i % 8) as f32);
+                    Instance::new(t).with_tint([1.0, 1.0 - i as f32 / 96.0, 0.5, 1.0])
+                })
+                .collect();
+            scene.add(
+                shapes::circle(point(0.0, 0.0), 2.0)
+                    .with_fill([0.2, 0.2, 0.2, 1.0])
+                    .with_instances(dots),
+            );
+            for j in 0..64 {
+                for i in 0..64 {
+                    let center = point(2.0 + 4.0 * i as f32, 2.0 + 4.0 * j as f32);
+                    let color = [0.5, 0.015 * i as f32, 0.015 * j as f32, 0.5];
+                    let shape = shapes::circle(center, 3.0)
+                        .with_stroke(color, StrokeOptions::default().with_line_width(0.5));
+                    // A gradient of its own for each of the first row.
+                    scene.add(if j == 0 {
+                        let gradient = LinearGradient::new(center - vector(3.0, 0.0), center)
+                            .with_stop(0.0, color)
+                            .with_stop(1.0, [0.0, 0.0, 0.0, 1.0]);
+                        shape.with_fill(gradient)
+                    } else {
+                        shape.with_fill(color)
+                    });
+                }
+            }
+        }
+        // Back to fewer, a group change touching several shapes, a moved
+        // clip and a few paints and instances changed in place.
+        _ => {
+            for &id in &ids[100..] {
+                scene.remove(id);
+            }
+            let clip = scene.get(ids[0]).unwrap().group.unwrap();
+            let group = scene.group_mut(clip).unwrap();
+            group.opacity = 0.5;
+            group.clip = Some(Clip::new(shapes::circle(point(160.0, 80.0), 48.0).path));
+            let dots = &mut scene.get_mut(ids[8]).unwrap().instances;
+            dots[40].tint = [0.0, 0.0, 1.0, 1.0];
+            dots[41].transform = Transform::translation(100.0, 140.0);
+            for &id in &ids[20..24] {
+                let fill = scene.get_mut(id).unwrap().fill.as_mut().unwrap();
+                fill.paint = [0.0, 0.6, 0.3, 1.0].into();
+            }
+        }
+    }
+}
+
+#[test]
+fn edits_between_frames() {
+    const STEPS: usize = 5;
+    let options = HeadlessOptions::default();
+    // One renderer drawing the scene after every edit...
+    let frames: Vec<Image> = {
+        let mut renderer = match pollster::block_on(HeadlessRenderer::new(SIZE, SIZE, &options)) {
+            Ok(renderer) => renderer,
+            Err(e) => {
+                eprintln!("Skipping golden image check: {}", e);
+                return;
+            }
+        };
+        let camera = renderer.default_camera();
+        let mut scene = white_scene();
+        (0..STEPS)
+            .map(|step| {
+                edit(&mut scene, step);
+                renderer.render(&scene, &camera)
+            })
+            .collect()
+    };
+    // ...has to draw what fresh renderers do.
+    for (step, frame) in frames.iter().enumerate() {
+        let mut scene = white_scene();
+        for i in 0..=step {
+            edit(&mut scene, i);
+        }
+        let expected = render(&scene, &options).unwrap();
+        let (different, _) = diff(&expected, frame);
+        assert_eq!(different, 0, "step {}: {} pixels differ", step, different);
+    }
+}
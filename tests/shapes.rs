@@ -0,0 +1,35 @@
+//! Ready-made shapes.
+
+use lyon::math::{point, Box2D};
+use lyon::path::builder::BorderRadii;
+use lyon::path::{Path, Winding};
+
+use wgpu_lyon::shapes;
+
+#[test]
+fn rounded_rect_scales_large_radii_like_css() {
+    let rect = Box2D::new(point(0.0, 0.0), point(40.0, 20.0));
+    let radii = BorderRadii {
+        top_left: 30.0,
+        top_right: 10.0,
+        bottom_left: 10.0,
+        bottom_right: 10.0,
+    };
+    // The left side is 20 long with 40 of radii along it, so all of them
+    // halve, not just the ones that overlap.
+    let expected = BorderRadii {
+        top_left: 15.0,
+        top_right: 5.0,
+        bottom_left: 5.0,
+        bottom_right: 5.0,
+    };
+    let mut builder = Path::builder();
+    builder.add_rounded_rectangle(&rect, &expected, Winding::Positive);
+    let expected = builder.build();
+
+    let actual = shapes::rounded_rect(rect, &radii).path;
+    assert_eq!(
+        actual.iter().collect::<Vec<_>>(),
+        expected.iter().collect::<Vec<_>>()
+    );
+}
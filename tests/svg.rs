@@ -0,0 +1,467 @@
+//! Parsing SVG path data, and importing and exporting SVG documents.
+
+use lyon::math::{point, size, vector, Angle, Box2D, Point, Transform};
+use lyon::path::{Event, Path, Winding};
+use lyon::tessellation::{FillRule, LineCap, LineJoin, StrokeOptions};
+
+use wgpu_lyon::dash::DashPattern;
+use wgpu_lyon::paint::{LinearGradient, Paint, RadialGradient, Spread};
+use wgpu_lyon::scene::{Clip, Group, Scene, Shape};
+use wgpu_lyon::svg::{
+    export, import, parse_path, path_data, ImportError, PathError, PathErrorKind,
+};
+
+fn events(path: &Path) -> Vec<Event<Point, Point>> {
+    path.iter().collect()
+}
+
+fn assert_close(a: Point, b: Point) {
+    assert!((a - b).length() < 1e-3, "{:?} != {:?}", a, b);
+}
+
+fn error(d: &str) -> (usize, PathErrorKind) {
+    let PathError { offset, kind } = parse_path(d).unwrap_err();
+    (offset, kind)
+}
+
+#[test]
+fn relative_commands_match_absolute_ones() {
+    let absolute = parse_path("M10 10 L20 10 H30 V20 C30 30 20 30 20 20 Q10 20 10 10 Z").unwrap();
+    let relative = parse_path("m10 10 l10 0 h10 v10 c0 10 -10 10 -10 0 q-10 0 -10 -10 z").unwrap();
+    assert_eq!(events(&absolute), events(&relative));
+}
+
+#[test]
+fn arguments_repeat_the_command() {
+    // Extra pairs after a move are lines.
+    let path = parse_path("M0 0 10 0 10 10 L0 10 0 5").unwrap();
+    let expected = parse_path("M0 0 L10 0 L10 10 L0 10 L0 5").unwrap();
+    assert_eq!(events(&path), events(&expected));
+}
+
+#[test]
+fn compact_numbers() {
+    let path = parse_path("M.5.5L-1-1,2e1-2E-1").unwrap();
+    let expected = parse_path("M 0.5 0.5 L -1 -1 L 20 -0.2").unwrap();
+    assert_eq!(events(&path), events(&expected));
+}
+
+#[test]
+fn smooth_curves_reflect_the_previous_control_point() {
+    let path = parse_path("M0 0 C0 10 10 10 10 0 S20 -10 20 0 M0 0 Q5 10 10 0 T20 0").unwrap();
+    let expected =
+        parse_path("M0 0 C0 10 10 10 10 0 C10 -10 20 -10 20 0 M0 0 Q5 10 10 0 Q15 -10 20 0")
+            .unwrap();
+    assert_eq!(events(&path), events(&expected));
+
+    // Without a curve of the same kind before, the control point is the
+    // current point.
+    let path = parse_path("M0 0 Q5 10 10 0 S20 10 20 0").unwrap();
+    let expected = parse_path("M0 0 Q5 10 10 0 C10 0 20 10 20 0").unwrap();
+    assert_eq!(events(&path), events(&expected));
+}
+
+#[test]
+fn arcs() {
+    // Compact flags, and a half circle above the x axis.
+    let path = parse_path("M0 0a5 5 0 0110 0").unwrap();
+    let mut last = point(0.0, 0.0);
+    for event in path.iter() {
+        if let Event::Quadratic { to, .. } = event {
+            assert!(to.y <= 1e-3, "{:?}", to);
+            assert!(
+                ((to - point(5.0, 0.0)).length() - 5.0).abs() < 1e-3,
+                "{:?}",
+                to
+            );
+            last = to;
+        }
+    }
+    assert_close(last, point(10.0, 0.0));
+
+    // Zero radii make a line.
+    let path = parse_path("M0 0 A0 5 0 1 1 10 0").unwrap();
+    let expected = parse_path("M0 0 L10 0").unwrap();
+    assert_eq!(events(&path), events(&expected));
+}
+
+#[test]
+fn close_returns_to_the_start() {
+    let path = parse_path("M10 10 h10 v10 z l5 0").unwrap();
+    let expected = parse_path("M10 10 H20 V20 Z M10 10 L15 10").unwrap();
+    assert_eq!(events(&path), events(&expected));
+}
+
+#[test]
+fn errors_report_byte_offsets() {
+    assert_eq!(parse_path("").unwrap().iter().count(), 0);
+    assert_eq!(error("L10 10"), (0, PathErrorKind::ExpectedMoveTo));
+    assert_eq!(error("  10 10"), (2, PathErrorKind::ExpectedMoveTo));
+    assert_eq!(error("M10 10 L20 x"), (11, PathErrorKind::ExpectedNumber));
+    assert_eq!(error("M10 10 L20"), (10, PathErrorKind::ExpectedNumber));
+    assert_eq!(
+        error("M0 0 Z 5"),
+        (7, PathErrorKind::UnexpectedCharacter('5'))
+    );
+    assert_eq!(
+        error("M0 0 é"),
+        (5, PathErrorKind::UnexpectedCharacter('é'))
+    );
+    assert_eq!(
+        error("M0 0 X"),
+        (5, PathErrorKind::UnexpectedCharacter('X'))
+    );
+    assert_eq!(error("M0 1e99"), (3, PathErrorKind::NumberOutOfRange));
+    assert_eq!(
+        error("M0 0 A1 1 0 2 0 1 1"),
+        (12, PathErrorKind::ExpectedFlag)
+    );
+    assert_eq!(
+        parse_path("M0 0 L").unwrap_err().to_string(),
+        "expected a number at byte 6"
+    );
+}
+
+fn import_shapes(svg: &str) -> (Scene, Vec<Shape>) {
+    let mut scene = Scene::new();
+    import(svg, &mut scene).unwrap();
+    let shapes = scene.iter().map(|(_, shape)| shape.clone()).collect();
+    (scene, shapes)
+}
+
+#[test]
+fn import_nests_transforms() {
+    let (_, shapes) = import_shapes(
+        r#"<svg xmlns="http://www.w3.org/2000/svg">
+            <g transform="translate(10 20)">
+                <g transform="scale(2)">
+                    <rect width="2" height="2" transform="rotate(90) translate(1, 0)"/>
+                </g>
+            </g>
+        </svg>"#,
+    );
+    assert_eq!(shapes.len(), 1);
+    // (0, 0) is moved right by 1, turned to point down, doubled and moved.
+    let p = shapes[0].transform.transform_point(point(0.0, 0.0));
+    assert_close(p, point(10.0, 22.0));
+}
+
+#[test]
+fn import_inherits_presentation_attributes() {
+    let (scene, shapes) = import_shapes(
+        r##"<svg xmlns="http://www.w3.org/2000/svg">
+            <g fill="red" stroke="#00f" stroke-width="3" opacity="0.5">
+                <circle r="5" fill-rule="evenodd" opacity="0.5"/>
+                <path d="M0 0 L10 0" style="fill: none; stroke-linejoin: round"/>
+                <polygon points="0,0 10,0 10,10" fill="rgb(0, 255, 0)" fill-opacity="0.25"/>
+            </g>
+            <line x2="10" stroke="black"/>
+        </svg>"##,
+    );
+    assert_eq!(shapes.len(), 4);
+
+    let circle = &shapes[0];
+    let fill = circle.fill.as_ref().unwrap();
+    assert_eq!(fill.paint, Paint::Solid([1.0, 0.0, 0.0, 1.0]));
+    assert_eq!(fill.options.fill_rule, FillRule::EvenOdd);
+    let stroke = circle.stroke.as_ref().unwrap();
+    assert_eq!(stroke.paint, Paint::Solid([0.0, 0.0, 1.0, 1.0]));
+    assert_eq!(stroke.options.line_width, 3.0);
+    // Opacity isn't inherited: the group has its own.
+    assert_eq!(circle.opacity, 0.5);
+    assert_eq!(scene.opacity(circle), 0.25);
+
+    let path = &shapes[1];
+    assert!(path.fill.is_none());
+    assert_eq!(
+        path.stroke.as_ref().unwrap().options.line_join,
+        LineJoin::Round
+    );
+    assert_eq!(path.opacity, 1.0);
+    assert_eq!(path.group, circle.group);
+
+    let polygon = &shapes[2];
+    let fill = polygon.fill.as_ref().unwrap();
+    assert_eq!(fill.paint, Paint::Solid([0.0, 1.0, 0.0, 0.25]));
+    assert_eq!(fill.options.fill_rule, FillRule::NonZero);
+
+    // Outside the group, the defaults apply again.
+    let line = &shapes[3];
+    assert!(line.group.is_none());
+    assert_eq!(
+        line.fill.as_ref().unwrap().paint,
+        Paint::Solid([0.0, 0.0, 0.0, 1.0])
+    );
+    assert_eq!(line.stroke.as_ref().unwrap().options.line_width, 1.0);
+}
+
+#[test]
+fn import_maps_the_view_box() {
+    let mut scene = Scene::new();
+    let document_size = import(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100px"
+                viewBox="-50 -50 100 100">
+            <rect x="-50" y="-50" width="100" height="100"/>
+        </svg>"#,
+        &mut scene,
+    )
+    .unwrap();
+    assert_eq!(document_size, size(200.0, 100.0));
+    // Scaled to fit the height, and centered horizontally.
+    let (_, shape) = scene.iter().next().unwrap();
+    assert_close(
+        shape.transform.transform_point(point(-50.0, -50.0)),
+        point(50.0, 0.0),
+    );
+    assert_close(
+        shape.transform.transform_point(point(50.0, 50.0)),
+        point(150.0, 100.0),
+    );
+}
+
+#[test]
+fn import_errors() {
+    let mut scene = Scene::new();
+    assert!(matches!(
+        import("<svg", &mut scene),
+        Err(ImportError::Xml(_))
+    ));
+    assert!(matches!(
+        import("<html/>", &mut scene),
+        Err(ImportError::NotSvg)
+    ));
+    // Unsupported and invalid content is skipped.
+    let document_size = import(
+        r#"<svg xmlns="http://www.w3.org/2000/svg">
+            <title>Skipped</title>
+            <text>Skipped</text>
+            <path d="M0 0 L10 x"/>
+            <rect width="10" height="0"/>
+            <rect width="10" height="10" transform="spin(4)"/>
+        </svg>"#,
+        &mut scene,
+    )
+    .unwrap();
+    assert_eq!(document_size, size(0.0, 0.0));
+    assert_eq!(scene.len(), 1);
+}
+
+#[test]
+fn import_gradients() {
+    let (_, shapes) = import_shapes(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">
+            <defs>
+                <linearGradient id="stops">
+                    <stop offset="0" stop-color="red"/>
+                    <stop offset="100%" style="stop-color: blue; stop-opacity: 0.5"/>
+                </linearGradient>
+                <radialGradient id="user" gradientUnits="userSpaceOnUse" cx="5" cy="5" r="5"
+                        fx="4" spreadMethod="reflect" xlink:href="#stops"/>
+            </defs>
+            <rect x="10" y="20" width="100" height="50" fill="url(#stops)"/>
+            <rect width="10" height="10" fill="url(#user)" fill-opacity="0.5"/>
+            <rect width="10" height="10" fill="url(#missing) green"/>
+        </svg>"##,
+    );
+
+    // Bounding box units are mapped to the shape's bounds.
+    let Paint::LinearGradient(linear) = &shapes[0].fill.as_ref().unwrap().paint else {
+        panic!("not a linear gradient");
+    };
+    assert_close(
+        linear.transform.transform_point(linear.start),
+        point(10.0, 20.0),
+    );
+    assert_close(
+        linear.transform.transform_point(linear.end),
+        point(110.0, 20.0),
+    );
+    assert_eq!(linear.stops.len(), 2);
+    assert_eq!(linear.stops[1].offset, 1.0);
+    assert_eq!(linear.stops[1].color, [0.0, 0.0, 1.0, 0.5]);
+
+    let Paint::RadialGradient(radial) = &shapes[1].fill.as_ref().unwrap().paint else {
+        panic!("not a radial gradient");
+    };
+    assert_eq!(radial.center, point(5.0, 5.0));
+    assert_eq!(radial.focal, point(4.0, 5.0));
+    assert_eq!(radial.spread, Spread::Reflect);
+    assert_eq!(radial.transform, Transform::identity());
+    // Stops from the linked gradient, faded by fill-opacity.
+    assert_eq!(radial.stops[0].color, [1.0, 0.0, 0.0, 0.5]);
+    assert_eq!(radial.stops[1].color, [0.0, 0.0, 1.0, 0.25]);
+
+    assert_eq!(
+        shapes[2].fill.as_ref().unwrap().paint,
+        Paint::Solid([0.0, 128.0 / 255.0, 0.0, 1.0])
+    );
+}
+
+#[test]
+fn path_data_round_trips() {
+    let d = "M0.1 -2 L3 4 Q5 6 7.25 8 C1 2 3 4 5 6 Z M10 10 L20 20";
+    let path = parse_path(d).unwrap();
+    assert_eq!(path_data(&path), d);
+    assert_eq!(
+        events(&parse_path(&path_data(&path)).unwrap()),
+        events(&path)
+    );
+}
+
+#[test]
+fn export_round_trips_through_import() {
+    let mut scene = Scene::new();
+    let outer = scene.add_group(Group::new().with_opacity(0.8));
+    let inner = scene.add_group(Group::new().with_parent(outer).with_opacity(0.5));
+
+    let mut builder = Path::builder();
+    builder.add_circle(point(0.0, 0.0), 10.0, Winding::Positive);
+    let circle = builder.build();
+    let arrow = wgpu_lyon::shapes::arrow();
+
+    scene.add(
+        Shape::new(arrow.clone())
+            .with_fill([1.0, 0.2, 0.0, 1.0])
+            .with_stroke(
+                [0.0, 0.0, 0.4, 0.6],
+                StrokeOptions::default()
+                    .with_line_width(0.1)
+                    .with_line_cap(LineCap::Round)
+                    .with_line_join(LineJoin::Bevel)
+                    .with_miter_limit(2.0),
+            )
+            .with_dash(DashPattern::new(&[0.5, 0.25, 0.125]).with_offset(0.1))
+            .with_transform(Transform::scale(100.0, 100.0).then_translate(vector(150.0, 100.0)))
+            .with_group(inner),
+    );
+    let linear = LinearGradient::new(point(-10.0, 0.0), point(10.0, 0.0))
+        .with_stop(0.0, [1.0, 1.0, 1.0, 1.0])
+        .with_stop(0.25, [0.2, 0.4, 0.6, 0.5])
+        // Off the 1/255 grid of hex colors.
+        .with_stop(0.75, [0.1234, 0.987, 1.0 / 3.0, 1.0])
+        .with_spread(Spread::Repeat)
+        .with_transform(Transform::rotation(Angle::degrees(30.0)));
+    scene.add(
+        Shape::new(circle.clone())
+            .with_fill(linear)
+            .with_fill_rule(FillRule::EvenOdd)
+            .with_transform(Transform::translation(50.0, 50.0))
+            .with_opacity(0.75)
+            .with_group(outer),
+    );
+    // Drawn between shapes of the groups, which splits them in the document.
+    let radial = RadialGradient::new(point(0.0, 0.0), 10.0)
+        .with_focal(point(2.0, -3.0))
+        .with_stop(0.0, [0.0, 0.0, 0.0, 1.0])
+        .with_stop(1.0, [1.0, 1.0, 1.0, 0.0]);
+    scene.add(
+        Shape::new(circle.clone())
+            .with_stroke(radial, StrokeOptions::default().with_line_width(4.0)),
+    );
+    scene.add(
+        Shape::new(circle)
+            .with_fill([0.5, 0.123, 0.9, 1.0])
+            .with_group(inner),
+    );
+
+    let svg = export(&scene, size(300.0, 200.0));
+    let mut imported = Scene::new();
+    assert_eq!(import(&svg, &mut imported).unwrap(), size(300.0, 200.0));
+    assert_eq!(export(&imported, size(300.0, 200.0)), svg);
+
+    let original = scene.draw_order();
+    let copies = imported.draw_order();
+    assert_eq!(original.len(), copies.len());
+    for (original_shape, copy) in original.into_iter().zip(copies) {
+        assert_eq!(events(&copy.path), events(&original_shape.path));
+        assert_eq!(copy.transform, original_shape.transform);
+        assert_eq!(imported.opacity(copy), scene.opacity(original_shape));
+        match (&copy.fill, &original_shape.fill) {
+            (Some(a), Some(b)) => {
+                assert_eq!(a.paint, b.paint);
+                assert_eq!(a.options.fill_rule, b.options.fill_rule);
+            }
+            (a, b) => assert_eq!(a.is_none(), b.is_none()),
+        }
+        match (&copy.stroke, &original_shape.stroke) {
+            (Some(a), Some(b)) => {
+                assert_eq!(a.paint, b.paint);
+                assert_eq!(a.options.line_width, b.options.line_width);
+                assert_eq!(a.options.start_cap, b.options.start_cap);
+                assert_eq!(a.options.end_cap, b.options.end_cap);
+                assert_eq!(a.options.line_join, b.options.line_join);
+                assert_eq!(a.options.miter_limit, b.options.miter_limit);
+                assert_eq!(a.dash, b.dash);
+            }
+            (a, b) => assert_eq!(a.is_none(), b.is_none()),
+        }
+    }
+}
+
+#[test]
+fn clip_paths() {
+    let (scene, shapes) = import_shapes(
+        r#"<svg xmlns="http://www.w3.org/2000/svg">
+            <defs>
+                <clipPath id="frame" transform="translate(5 0)">
+                    <rect width="10" height="10"/>
+                    <rect x="2" y="2" width="6" height="6" clip-rule="evenodd"/>
+                </clipPath>
+                <clipPath id="dot" clip-rule="evenodd">
+                    <circle cx="3" cy="3" r="2"/>
+                </clipPath>
+            </defs>
+            <g clip-path="url(#frame)" transform="scale(2)">
+                <rect width="4" height="4" style="clip-path: url(#dot)"/>
+                <rect width="4" height="4" clip-path="url(#missing)"/>
+            </g>
+        </svg>"#,
+    );
+    assert_eq!(shapes.len(), 2);
+    let own = scene.group(shapes[0].group.unwrap()).unwrap();
+    let dot = own.clip.as_ref().unwrap();
+    assert_eq!(dot.fill_rule, FillRule::EvenOdd);
+    assert_eq!(dot.transform, Transform::scale(2.0, 2.0));
+    let outer = scene.group(own.parent.unwrap()).unwrap();
+    let frame = outer.clip.as_ref().unwrap();
+    // Each shape keeps its own rule, inherited or not.
+    assert_eq!(frame.fill_rule, FillRule::NonZero);
+    assert_eq!(frame.union.len(), 1);
+    assert_eq!(frame.union[0].1, FillRule::EvenOdd);
+    assert_eq!(
+        frame.transform,
+        Transform::translation(5.0, 0.0).then_scale(2.0, 2.0)
+    );
+    // Unknown references are ignored.
+    assert_eq!(shapes[1].group, own.parent);
+
+    // Clips are written as <clipPath>s and read back the same.
+    let mut scene = Scene::new();
+    let mut square = Path::builder();
+    square.add_rectangle(
+        &Box2D::new(point(0.0, 0.0), point(10.0, 10.0)),
+        Winding::Positive,
+    );
+    let clip = Clip::new(square.build())
+        .with_transform(Transform::translation(5.0, 5.0))
+        .with_fill_rule(FillRule::EvenOdd)
+        .with_union(circle_path(), FillRule::NonZero);
+    let group = scene.add_group(Group::new().with_clip(clip));
+    scene.add(
+        Shape::new(circle_path())
+            .with_fill([1.0, 0.0, 0.0, 1.0])
+            .with_group(group),
+    );
+    let svg = export(&scene, size(20.0, 20.0));
+    assert!(svg.contains("<clipPath id=\"clip1\" transform=\"matrix(1 0 0 1 5 5)\">"));
+    assert!(svg.contains("clip-path=\"url(#clip1)\""));
+    let mut imported = Scene::new();
+    import(&svg, &mut imported).unwrap();
+    assert_eq!(export(&imported, size(20.0, 20.0)), svg);
+}
+
+fn circle_path() -> Path {
+    let mut builder = Path::builder();
+    builder.add_circle(point(10.0, 10.0), 8.0, Winding::Positive);
+    builder.build()
+}